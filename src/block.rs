@@ -0,0 +1,87 @@
+use std::ops::Range;
+
+use unicode_width::UnicodeWidthChar;
+
+/* @brief a rectangular selection, corners are (render column, row) so
+ *        that tabs and wide characters line up the way they are drawn
+ */
+#[derive(Clone, Copy)]
+pub struct Block {
+    pub anchor: (usize, usize),
+    pub cursor: (usize, usize),
+}
+
+impl Block {
+    pub fn new(col: usize, row: usize) -> Self {
+        Self {
+            anchor: (col, row),
+            cursor: (col, row),
+        }
+    }
+
+    pub fn rows(&self) -> Range<usize> {
+        self.anchor.1.min(self.cursor.1)..self.anchor.1.max(self.cursor.1) + 1
+    }
+
+    // an empty range is a column between characters, typing inserts there
+    pub fn columns(&self) -> Range<usize> {
+        self.anchor.0.min(self.cursor.0)..self.anchor.0.max(self.cursor.0)
+    }
+
+    // moves both corners to column col, leaving an empty column
+    pub fn collapse(&mut self, col: usize) {
+        self.anchor.0 = col;
+        self.cursor.0 = col;
+    }
+}
+
+fn char_width(chr: char, col: usize, tab_stop: usize) -> usize {
+    if chr == '\t' {
+        tab_stop - col % tab_stop
+    } else {
+        chr.width().unwrap_or(0)
+    }
+}
+
+/* @brief the byte where render column col starts in row along with the
+ *        spaces needed to reach it when the row is shorter, a column inside
+ *        a tab or wide character is taken as the start of the next one
+ */
+pub fn byte_at_column(row: &str, col: usize, tab_stop: usize) -> (usize, usize) {
+    let mut render = 0;
+    for (idx, chr) in row.char_indices() {
+        if render >= col {
+            return (idx, 0);
+        }
+        render += char_width(chr, render, tab_stop);
+    }
+    (row.len(), col.saturating_sub(render))
+}
+
+// bytes of the characters of row that start inside columns
+pub fn byte_range(row: &str, columns: Range<usize>, tab_stop: usize) -> Range<usize> {
+    let (start, _) = byte_at_column(row, columns.start, tab_stop);
+    let (end, _) = byte_at_column(row, columns.end, tab_stop);
+    start..end.max(start)
+}
+
+// the render width of row
+pub fn width(row: &str, tab_stop: usize) -> usize {
+    row.chars()
+        .fold(0, |col, chr| col + char_width(chr, col, tab_stop))
+}
+
+/* @brief bytes of the character drawn over render column col, the column
+ *        just left of a block that backspace deletes from
+ */
+pub fn char_at_column(row: &str, col: usize, tab_stop: usize) -> Option<Range<usize>> {
+    let mut render = 0;
+    for (idx, chr) in row.char_indices() {
+        let width = char_width(chr, render, tab_stop);
+        if col < render + width.max(1) {
+            return (render <= col).then_some(idx..idx + chr.len_utf8());
+        }
+        render += width;
+    }
+    None
+}
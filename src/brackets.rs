@@ -0,0 +1,91 @@
+/* @brief bracket matching over the whole buffer, there is no syntax
+ *        highlighting so brackets inside strings and comments count too
+ */
+
+use crate::rows::EditorRows;
+
+const PAIRS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}')];
+
+// the pair chr belongs to and whether it opens it
+fn pair_of(chr: char) -> Option<((char, char), bool)> {
+    PAIRS.iter().find_map(|pair| match chr {
+        _ if chr == pair.0 => Some((*pair, true)),
+        _ if chr == pair.1 => Some((*pair, false)),
+        _ => None,
+    })
+}
+
+pub fn bracket_at(rows: &EditorRows, x: usize, y: usize) -> Option<char> {
+    if y >= rows.num_rows() {
+        return None;
+    }
+    rows.get_row(y)
+        .row_content
+        .get(x..)
+        .and_then(|rest| rest.chars().next())
+        .filter(|chr| pair_of(*chr).is_some())
+}
+
+/* @brief the position of the bracket matching the one at x, y, scanning
+ *        forwards from an opener and backwards from a closer across rows
+ *        and skipping nested pairs of the same kind
+ */
+pub fn find_match(rows: &EditorRows, x: usize, y: usize) -> Option<(usize, usize)> {
+    let ((opener, closer), opens) = pair_of(bracket_at(rows, x, y)?)?;
+    let mut depth = 0usize;
+    let mut visit = |chr: char| {
+        if chr == opener {
+            depth = if opens { depth + 1 } else { depth.saturating_sub(1) };
+        } else if chr == closer {
+            depth = if opens { depth.saturating_sub(1) } else { depth + 1 };
+        }
+        depth == 0 && (chr == opener || chr == closer)
+    };
+    if opens {
+        for at in y..rows.num_rows() {
+            let content = &rows.get_row(at).row_content;
+            let from = if at == y { x } else { 0 };
+            for (idx, chr) in content[from..].char_indices() {
+                if visit(chr) {
+                    return Some((from + idx, at));
+                }
+            }
+        }
+    } else {
+        for at in (0..=y).rev() {
+            let content = &rows.get_row(at).row_content;
+            let to = if at == y { x + closer.len_utf8() } else { content.len() };
+            for (idx, chr) in content[..to].char_indices().rev() {
+                if visit(chr) {
+                    return Some((idx, at));
+                }
+            }
+        }
+    }
+    None
+}
+
+/* @brief positions of the brackets in rows that have no partner, closers
+ *        that don't fit the innermost open bracket and openers left open
+ */
+pub fn unmatched(rows: &EditorRows) -> Vec<(usize, usize)> {
+    let mut open: Vec<(char, usize, usize)> = Vec::new();
+    let mut unmatched = Vec::new();
+    for at in 0..rows.num_rows() {
+        for (idx, chr) in rows.get_row(at).row_content.char_indices() {
+            match pair_of(chr) {
+                Some((_, true)) => open.push((chr, idx, at)),
+                Some(((opener, _), false)) => {
+                    if open.last().is_some_and(|(last, _, _)| *last == opener) {
+                        open.pop();
+                    } else {
+                        unmatched.push((idx, at));
+                    }
+                }
+                None => (),
+            }
+        }
+    }
+    unmatched.extend(open.into_iter().map(|(_, x, y)| (x, y)));
+    unmatched
+}
@@ -0,0 +1,66 @@
+use std::path::Path;
+
+use crate::cursor_controller::CursorController;
+use crate::grep::{Change, Hit};
+use crate::rows::EditorRows;
+use crate::undo::UndoHistory;
+
+/* @brief what a list buffer lists, the rows of a grep list after the first
+ *        line up with its hits while a replace preview keeps which change
+ *        is on each row, None for its headings
+ */
+pub enum List {
+    Grep(Vec<Hit>),
+    Replace(Vec<Change>, Vec<Option<usize>>),
+}
+
+impl List {
+    pub fn title(&self) -> &'static str {
+        match self {
+            List::Grep(_) => "[grep]",
+            List::Replace(..) => "[replace]",
+        }
+    }
+}
+
+/* @brief a buffer that isn't on screen together with the state that goes
+ *        with it, swapped with the one on screen when switching buffers
+ */
+pub struct Background {
+    pub editor_rows: EditorRows,
+    pub cursor_controller: CursorController,
+    pub undo: UndoHistory,
+    pub dirty: u64,
+    pub list: Option<List>,
+}
+
+impl Background {
+    pub fn new(editor_rows: EditorRows, list: Option<List>, size: (usize, usize)) -> Self {
+        Self {
+            editor_rows,
+            cursor_controller: CursorController::new(size),
+            undo: UndoHistory::new(),
+            dirty: 0,
+            list,
+        }
+    }
+
+    // an unnamed buffer that was never typed into, dropped rather than kept around
+    pub fn is_unused(&self) -> bool {
+        self.editor_rows.filename.is_none()
+            && self.editor_rows.num_rows() == 0
+            && self.dirty == 0
+            && self.list.is_none()
+    }
+
+    pub fn is_file(&self, path: &Path) -> bool {
+        self.editor_rows.filename.as_deref().is_some_and(|name| same_file(name, path))
+    }
+}
+
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
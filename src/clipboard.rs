@@ -0,0 +1,212 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::buffer::Buf;
+use crate::config::Config;
+
+// copied text, a block copy pastes back as a rectangle
+#[derive(Clone)]
+pub struct Clip {
+    pub text: String,
+    pub block: bool,
+}
+
+// commands the clipboard setting can name instead of spelling them out
+const KNOWN_COMMANDS: &[(&str, &str, &str)] = &[
+    ("xclip", "xclip -selection clipboard", "xclip -selection clipboard -o"),
+    ("wl-copy", "wl-copy", "wl-paste --no-newline"),
+    ("pbcopy", "pbcopy", "pbpaste"),
+];
+
+/* @brief where copied text goes besides the editor itself
+ */
+enum Backend {
+    // an OSC 52 escape sequence asks the terminal to set the clipboard,
+    //  which works over ssh but can't be read back
+    Osc52,
+    // shell commands, copy is given the text on stdin and paste prints it
+    Command { copy: String, paste: Option<String> },
+    // the text stays inside the editor
+    Internal,
+}
+
+/* @brief the clipboard picked by the `clipboard` setting, one of osc52,
+ *        internal, xclip, wl-copy, pbcopy or command to run the
+ *        clipboard_copy and clipboard_paste commands, the last copy is
+ *        always kept too and pasted when the backend can't be read
+ */
+pub struct Clipboard {
+    backend: Backend,
+    internal: Option<Clip>,
+}
+
+impl Clipboard {
+    pub fn new(config: &Config, filetype: &str) -> Self {
+        let setting: String = config
+            .get(filetype, "clipboard")
+            .unwrap_or_else(|| String::from("internal"));
+        let backend = match setting.as_str() {
+            "osc52" => Backend::Osc52,
+            "command" => match config.get(filetype, "clipboard_copy") {
+                Some(copy) => Backend::Command {
+                    copy,
+                    paste: config.get(filetype, "clipboard_paste"),
+                },
+                None => Backend::Internal,
+            },
+            name => match KNOWN_COMMANDS.iter().find(|(known, _, _)| *known == name) {
+                Some((_, copy, paste)) => Backend::Command {
+                    copy: copy.to_string(),
+                    paste: Some(paste.to_string()),
+                },
+                None => Backend::Internal,
+            },
+        };
+        Self {
+            backend,
+            internal: None,
+        }
+    }
+
+    /* @brief keeps clip and hands its text to the backend, the OSC 52
+     *        sequence goes out with the next refresh of buffer
+     */
+    pub fn copy(&mut self, clip: Clip, buffer: &mut Buf) -> io::Result<()> {
+        let result = match &self.backend {
+            Backend::Osc52 => {
+                buffer.push_str(&format!("\x1b]52;c;{}\x07", STANDARD.encode(&clip.text)));
+                Ok(())
+            }
+            Backend::Command { copy, .. } => run_copy(copy, &clip.text),
+            Backend::Internal => Ok(()),
+        };
+        self.internal = Some(clip);
+        result
+    }
+
+    /* @brief what to paste, the system clipboard when the backend can read
+     *        it, text that is still the last copy keeps its block shape
+     */
+    pub fn paste(&self) -> Option<Clip> {
+        let Backend::Command { paste: Some(paste), .. } = &self.backend else {
+            return self.internal.clone();
+        };
+        match run_paste(paste) {
+            Some(text) if self.internal.as_ref().is_some_and(|clip| clip.text == text) => {
+                self.internal.clone()
+            }
+            Some(text) if !text.is_empty() => Some(Clip { text, block: false }),
+            _ => self.internal.clone(),
+        }
+    }
+}
+
+fn run_copy(command: &str, text: &str) -> io::Result<()> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // the pipe closes when stdin is dropped, so the command sees the end
+    child.stdin.take().map_or(Ok(()), |mut stdin| stdin.write_all(text.as_bytes()))?;
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{} failed with {}", command, status)))
+    }
+}
+
+fn run_paste(command: &str) -> Option<String> {
+    let output = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    // a file only this test writes to, so tests running at once don't clash
+    fn scratch_file(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("text_editor_clipboard_{}_{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    // a command backend that keeps the clipboard in path
+    fn file_clipboard(path: &Path) -> Clipboard {
+        let path = path.display();
+        Clipboard {
+            backend: Backend::Command {
+                copy: format!("cat > '{}'", path),
+                paste: Some(format!("cat '{}'", path)),
+            },
+            internal: None,
+        }
+    }
+
+    #[test]
+    fn run_copy_hands_the_text_over_on_stdin() {
+        let path = scratch_file("run_copy");
+        run_copy(&format!("cat > '{}'", path.display()), "one\ntwo ü\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo ü\n");
+        assert!(run_copy("exit 3", "text").is_err());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn run_paste_reads_stdout() {
+        assert_eq!(run_paste("printf 'a\\nb'").as_deref(), Some("a\nb"));
+        assert_eq!(run_paste("printf x; exit 1"), None);
+    }
+
+    #[test]
+    fn pastes_what_the_command_prints() {
+        let path = scratch_file("paste");
+        let mut clipboard = file_clipboard(&path);
+        let clip = Clip {
+            text: String::from("a\nb"),
+            block: true,
+        };
+        clipboard.copy(clip, &mut Buf::new()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\nb");
+        // still the last copy, so it keeps its block shape
+        let pasted = clipboard.paste().unwrap();
+        assert_eq!((pasted.text.as_str(), pasted.block), ("a\nb", true));
+        // copied elsewhere since, so it is plain text
+        fs::write(&path, "other").unwrap();
+        let pasted = clipboard.paste().unwrap();
+        assert_eq!((pasted.text.as_str(), pasted.block), ("other", false));
+        // an empty clipboard falls back to the last copy
+        fs::write(&path, "").unwrap();
+        assert_eq!(clipboard.paste().unwrap().text, "a\nb");
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn pastes_the_last_copy_when_the_command_fails() {
+        let path = scratch_file("missing");
+        let mut clipboard = file_clipboard(&path);
+        assert!(clipboard.paste().is_none());
+        clipboard.internal = Some(Clip {
+            text: String::from("kept"),
+            block: false,
+        });
+        assert_eq!(clipboard.paste().unwrap().text, "kept");
+    }
+}
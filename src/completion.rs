@@ -0,0 +1,138 @@
+use std::{cmp::min, env, fs, path::PathBuf};
+
+use crate::input_line::InputLine;
+
+/* @brief expands a leading ~ to the home directory
+ */
+pub fn expand_tilde(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), env::var_os("HOME")) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            PathBuf::from(home).join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/* @brief paths in the directory named by input that start with the last
+ *        component of input, written the way the input was written,
+ *        directories end in a /
+ */
+fn path_candidates(input: &str) -> Vec<String> {
+    // a bare ~ only ever completes to the home directory
+    if input == "~" {
+        return vec![String::from("~/")];
+    }
+    let (dir_part, prefix) = match input.rfind('/') {
+        Some(idx) => input.split_at(idx + 1),
+        None => ("", input),
+    };
+    let dir = if dir_part.is_empty() {
+        PathBuf::from(".")
+    } else {
+        expand_tilde(dir_part)
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut candidates: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            // hidden files are only offered once a . has been typed
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let indicator = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", dir_part, name, indicator))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+fn common_prefix(candidates: &[String]) -> String {
+    let Some(first) = candidates.first() else {
+        return String::new();
+    };
+    let len = candidates[1..].iter().fold(first.len(), |len, candidate| {
+        first[..len]
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            // no mismatch means the shorter one is a prefix of the other
+            .map_or(min(len, candidate.len()), |((idx, _), _)| idx)
+    });
+    first[..len].to_string()
+}
+
+/* @brief tab completion state for a file prompt
+ *        the first Tab completes the longest common prefix, once nothing
+ *        more can be completed each Tab cycles through the candidates
+ */
+pub struct PathCompleter {
+    candidates: Vec<String>,
+    index: Option<usize>,
+}
+
+impl PathCompleter {
+    pub fn new() -> Self {
+        Self {
+            candidates: Vec::new(),
+            index: None,
+        }
+    }
+
+    // forget the candidates once anything other than Tab is pressed
+    pub fn reset(&mut self) {
+        self.candidates.clear();
+        self.index = None;
+    }
+
+    pub fn complete(&mut self, input: &mut InputLine) {
+        if !self.candidates.is_empty()
+            && (self.index.is_some() || input.as_str() == common_prefix(&self.candidates))
+        {
+            let next = self.index.map_or(0, |idx| (idx + 1) % self.candidates.len());
+            input.set(&self.candidates[next]);
+            self.index = Some(next);
+            return;
+        }
+        self.candidates = path_candidates(input.as_str());
+        self.index = None;
+        match self.candidates.len() {
+            0 => (),
+            1 => {
+                input.set(&self.candidates[0]);
+                self.candidates.clear();
+            }
+            _ => {
+                let prefix = common_prefix(&self.candidates);
+                if prefix.len() > input.as_str().len() {
+                    input.set(&prefix);
+                }
+            }
+        }
+    }
+
+    /* @brief candidate names listed after the prompt, the selected one
+     *        wrapped in <>
+     */
+    pub fn hint(&self) -> String {
+        if self.candidates.len() < 2 {
+            return String::new();
+        }
+        self.candidates
+            .iter()
+            .enumerate()
+            .fold(String::from("  "), |mut accm, (idx, candidate)| {
+                let trimmed = candidate.trim_end_matches('/');
+                let name = &candidate[trimmed.rfind('/').map_or(0, |idx| idx + 1)..];
+                if Some(idx) == self.index {
+                    accm.push_str(&format!("<{}> ", name));
+                } else {
+                    accm.push_str(&format!("{} ", name));
+                }
+                accm
+            })
+    }
+}
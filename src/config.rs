@@ -0,0 +1,170 @@
+use std::{collections::HashMap, env, fs, path::{Path, PathBuf}, str::FromStr};
+
+use crate::file_format::FileFormat;
+
+/* @brief directory holding the editor's config and state files,
+ *        $XDG_CONFIG_HOME/text_editor or ~/.config/text_editor
+ */
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("text_editor"))
+}
+
+/* @brief how a buffer is indented and how its tabs are displayed
+ */
+#[derive(Clone, Copy)]
+pub struct IndentSettings {
+    // columns a tab character is displayed as
+    pub tab_width: usize,
+    // columns in a level of indentation, the tab width when None
+    pub indent_width: Option<usize>,
+    // indent with spaces instead of a tab character
+    pub expand_tabs: bool,
+}
+
+impl Default for IndentSettings {
+    fn default() -> Self {
+        Self {
+            tab_width: 8,
+            indent_width: None,
+            expand_tabs: false,
+        }
+    }
+}
+
+// shown on the status bar, e.g. spaces:4
+impl std::fmt::Display for IndentSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.expand_tabs {
+            write!(f, "spaces:{}", self.indent_width())
+        } else {
+            write!(f, "tabs:{}", self.tab_width)
+        }
+    }
+}
+
+impl IndentSettings {
+    // settings from a more specific source replace the ones it gives
+    pub fn apply(self, overrides: IndentOverrides) -> Self {
+        Self {
+            tab_width: overrides.tab_width.unwrap_or(self.tab_width),
+            indent_width: overrides.indent_width.or(self.indent_width),
+            expand_tabs: overrides.expand_tabs.unwrap_or(self.expand_tabs),
+        }
+    }
+
+    pub fn indent_width(&self) -> usize {
+        self.indent_width.unwrap_or(self.tab_width)
+    }
+
+    // the text making up one level of indentation
+    pub fn unit(&self) -> String {
+        if self.expand_tabs {
+            " ".repeat(self.indent_width())
+        } else {
+            String::from("\t")
+        }
+    }
+}
+
+/* @brief the indentation settings a single source gives, None for the
+ *        ones it leaves to less specific sources
+ */
+#[derive(Clone, Copy, Default)]
+pub struct IndentOverrides {
+    pub tab_width: Option<usize>,
+    pub indent_width: Option<usize>,
+    pub expand_tabs: Option<bool>,
+}
+
+// name of the per project config file, looked for from a file's directory upwards
+const PROJECT_CONFIG: &str = ".text_editor";
+
+/* @brief settings read from a config file, one `key = value` per line
+ *        with # starting a comment, keys under a `[filetype]` header only
+ *        apply to that filetype, the global file lives in config_dir
+ */
+#[derive(Default)]
+pub struct Config {
+    global: HashMap<String, String>,
+    filetypes: HashMap<String, HashMap<String, String>>,
+}
+
+impl Config {
+    pub fn load() -> Self {
+        Self::read(config_dir().map(|dir| dir.join("config")).as_deref())
+    }
+
+    /* @brief the config file of the project file belongs to, the nearest
+     *        .text_editor in its directory or one of the parents
+     */
+    pub fn load_project(file: &Path) -> Self {
+        let path = file
+            .canonicalize()
+            .ok()
+            .and_then(|file| {
+                file.ancestors()
+                    .skip(1)
+                    .map(|dir| dir.join(PROJECT_CONFIG))
+                    .find(|path| path.is_file())
+            });
+        Self::read(path.as_deref())
+    }
+
+    fn read(path: Option<&Path>) -> Self {
+        let mut config = Self::default();
+        let content = path.and_then(|path| fs::read_to_string(path).ok());
+        let mut section: Option<String> = None;
+        for line in content
+            .iter()
+            .flat_map(|content| content.lines())
+            .map(|line| line.split('#').next().unwrap_or("").trim())
+        {
+            if let Some(filetype) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                section = Some(filetype.trim().to_string());
+            } else if let Some((key, value)) = line.split_once('=') {
+                let values = match &section {
+                    Some(filetype) => config.filetypes.entry(filetype.clone()).or_default(),
+                    None => &mut config.global,
+                };
+                values.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+        config
+    }
+
+    /* @brief the value of key for filetype, its section taking precedence
+     *        over the global keys, values that don't parse count as unset
+     */
+    pub fn get<T: FromStr>(&self, filetype: &str, key: &str) -> Option<T> {
+        self.filetypes
+            .get(filetype)
+            .and_then(|values| values.get(key))
+            .and_then(|value| value.parse().ok())
+            .or_else(|| self.global.get(key).and_then(|value| value.parse().ok()))
+    }
+
+    pub fn indent(&self, filetype: &str) -> IndentOverrides {
+        IndentOverrides {
+            tab_width: self.get(filetype, "tab_width").filter(|width| *width > 0),
+            indent_width: self.get(filetype, "indent_width").filter(|width| *width > 0),
+            expand_tabs: self.get(filetype, "expand_tabs"),
+        }
+    }
+
+    // the save time clean up asked for, the rest of the format is left alone
+    pub fn apply_format(&self, filetype: &str, format: &mut FileFormat) {
+        if let Some(trim) = self.get(filetype, "trim_trailing_whitespace") {
+            format.trim_trailing_whitespace = trim;
+        }
+        if let Some(trim) = self.get(filetype, "trim_trailing_blank_lines") {
+            format.trim_trailing_blank_lines = trim;
+        }
+        if let Some(final_newline) = self.get(filetype, "final_newline") {
+            format.final_newline = final_newline;
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use crossterm::event::{KeyCode};
 use crate::rows::EditorRows;
 use std::cmp::{min};
-use crate::rows::{Row, TAB_STOP};
+use crate::rows::Row;
+use crate::words;
+use unicode_width::UnicodeWidthChar;
 
 #[derive(Clone)] 
 pub struct CursorController {
@@ -21,6 +23,9 @@ pub struct CursorController {
     // pseudo cursol assuming tab expansion, can be greater than row length
     // used for rendering 
     pub render_x: usize,
+
+    // the other end of the selection, the cursor being one end
+    pub anchor: Option<(usize, usize)>,
 }
 
 impl CursorController {
@@ -33,16 +38,47 @@ impl CursorController {
             row_offset: 0,
             col_offset: 0,
             render_x: 0, // the actual cursor when accounting for tabs
+            anchor: None,
         }
     }
 
+    /* @brief start and end of the selection as (x, y) in the buffer, ordered
+     *        so that start comes first, the end is not selected
+     */
+    pub fn selection(&self) -> Option<((usize, usize), (usize, usize))> {
+        let cursor = (self.cursor_x, self.cursor_y);
+        self.anchor
+            .filter(|anchor| *anchor != cursor)
+            .map(|anchor| {
+                if (anchor.1, anchor.0) < (cursor.1, cursor.0) {
+                    (anchor, cursor)
+                } else {
+                    (cursor, anchor)
+                }
+            })
+    }
+
+    /* @brief rows touched by the selection, a selection ending at the start
+     *        of a row doesn't include that row
+     */
+    pub fn selected_rows(&self) -> Option<std::ops::Range<usize>> {
+        self.selection().map(|((_, start_y), (end_x, end_y))| {
+            if end_x == 0 && end_y > start_y {
+                start_y..end_y
+            } else {
+                start_y..end_y + 1
+            }
+        })
+    }
+
     pub fn move_cursor(&mut self, direction: KeyCode, editor_rows: &EditorRows) {
         let y_lim = editor_rows.num_rows();
-        let x_lim = if self.cursor_y >= y_lim {
-            0
+        let row = if self.cursor_y >= y_lim {
+            ""
         } else {
-            editor_rows.get_render(self.cursor_y).len()
+            editor_rows.get_row(self.cursor_y).row_content.as_str()
         };
+        let x_lim = row.len();
         let x_lim_above = if self.cursor_y == 0 {
             0
         } else {
@@ -65,17 +101,17 @@ impl CursorController {
             KeyCode::Left | KeyCode::Char('h') => {
                 if self.cursor_x == 0 && self.cursor_y != 0 {
                     self.cursor_x = x_lim_above;
-                    self.cursor_y = self.cursor_y - 1;
-                } else if self.cursor_x > 0 {
-                    self.cursor_x -= 1;
+                    self.cursor_y -= 1;
+                } else if let Some((prev, _)) = row[..self.cursor_x].char_indices().next_back() {
+                    self.cursor_x = prev;
                 }
             },
             KeyCode::Right | KeyCode::Char('l') => {
-                if self.render_x == x_lim {
+                if self.cursor_x >= x_lim {
                     self.cursor_x = 0;
                     self.cursor_y = min(y_lim, self.cursor_y + 1);
-                } else if self.cursor_x < x_lim {
-                    self.cursor_x += 1;
+                } else if let Some(chr) = row[self.cursor_x..].chars().next() {
+                    self.cursor_x += chr.len_utf8();
                 }
             },
             // convieniece keys
@@ -93,15 +129,49 @@ impl CursorController {
             }
             _ => unimplemented!(),
         }
+        self.clamp_x(editor_rows);
+    }
 
-        let row_len = if self.cursor_y < y_lim {
-            editor_rows.get_render(self.cursor_y).len()
+    /* @brief moves the cursor to the start of the previous word or the end
+     *        of the next one, stopping at the ends of rows before crossing
+     *        to the row above or below
+     */
+    pub fn move_word(&mut self, direction: KeyCode, editor_rows: &EditorRows) {
+        let y_lim = editor_rows.num_rows();
+        let row = if self.cursor_y >= y_lim {
+            ""
         } else {
-            0
+            editor_rows.get_row(self.cursor_y).row_content.as_str()
+        };
+        let extra = editor_rows.word_chars();
+        match direction {
+            KeyCode::Left if self.cursor_x == 0 => self.move_cursor(KeyCode::Left, editor_rows),
+            KeyCode::Left => {
+                self.cursor_x = words::prev_word_start(row, self.cursor_x, extra).unwrap_or(0);
+            }
+            KeyCode::Right if self.cursor_x >= row.len() => {
+                self.move_cursor(KeyCode::Right, editor_rows)
+            }
+            KeyCode::Right => {
+                self.cursor_x = words::next_word_end(row, self.cursor_x, extra).unwrap_or(row.len());
+            }
+            // only Left and Right move by words
+            _ => {}
+        }
+    }
+
+    // keeps cursor_x inside its row and on a character boundary
+    pub fn clamp_x(&mut self, editor_rows: &EditorRows) {
+        let row = if self.cursor_y < editor_rows.num_rows() {
+            editor_rows.get_row(self.cursor_y).row_content.as_str()
+        } else {
+            ""
         };
-        self.cursor_x = min(self.cursor_x, row_len);
+        self.cursor_x = min(self.cursor_x, row.len());
+        while !row.is_char_boundary(self.cursor_x) {
+            self.cursor_x -= 1;
+        }
     }
-    
 
     /* @brief adjusts column and row offsets so that the cursor in the buffer
      *        is always rendered in the terminal
@@ -112,7 +182,7 @@ impl CursorController {
         //  4 spaces
         (self.render_x, self.cursor_x) = 
             if self.cursor_y < editor_rows.num_rows() {
-                (self.get_render_x(&editor_rows.get_row(self.cursor_y), self.cursor_x),
+                (self.get_render_x(editor_rows.get_row(self.cursor_y), self.cursor_x, editor_rows.indent().tab_width),
                 min(editor_rows.get_row(self.cursor_y).row_content.len(), 
                     self.cursor_x))
             } else {
@@ -137,16 +207,16 @@ impl CursorController {
 
     }
     
-    pub fn get_render_x(&mut self, row: &Row, pos: usize) -> usize {
+    pub fn get_render_x(&self, row: &Row, pos: usize, tab_stop: usize) -> usize {
         let cursor_x = min(row.row_content.len(), pos);
         row.row_content[..cursor_x]
             .chars()
             .fold(0, |accm, chr| {
                 if chr == '\t' {
                     // move to tab stp to left and hop right one tab
-                    accm - (accm % TAB_STOP) + TAB_STOP
+                    accm - (accm % tab_stop) + tab_stop
                 } else {
-                    accm + 1
+                    accm + chr.width().unwrap_or(0)
                 }
             })
     }
@@ -1,9 +1,19 @@
-use crossterm::{event, terminal};
+use std::io::stdout;
+
+use crossterm::{event, execute, terminal};
 use event::{Event, KeyCode, KeyModifiers};
 
+use crate::completion::{expand_tilde, PathCompleter};
+use crate::history::PromptKind;
+use crate::input_line::InputLine;
+use crate::macros::MacroRegisters;
 use crate::output::Output;
 use crate::prompt;
-use crate::reader::Reader;
+
+// times a macro repeated until a search fails runs at most
+const MACRO_REPEAT_LIMIT: usize = 10_000;
+// how deep macros playing other macros may nest
+const MACRO_DEPTH_LIMIT: usize = 16;
 
 // @brief stores clean up code in drop
 pub struct CleanUp;
@@ -12,28 +22,102 @@ impl Drop for CleanUp {
     fn drop(&mut self) {
         // println!("\x1b[2J"); // clears screen with esc characters
         if terminal::is_raw_mode_enabled().unwrap() {
+            execute!(stdout(), event::DisableBracketedPaste).expect("Couldn't disable bracketed paste");
             terminal::disable_raw_mode().expect("Couldn't disable raw mode");
         }
     }
 }
 
+/* @brief keys that leave the buffer as it is, the only ones list buffers
+ *        take: movement, searching, copying and switching buffers, movement
+ *        keys count only with the modifiers that move or select since e.g.
+ *        ALT + Up moves rows
+ */
+fn is_read_only(key: &event::KeyEvent) -> bool {
+    let moves = [
+        KeyModifiers::NONE,
+        KeyModifiers::SHIFT,
+        KeyModifiers::CONTROL,
+        KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+    ]
+    .contains(&key.modifiers);
+    match key.code {
+        KeyCode::Up
+        | KeyCode::Down
+        | KeyCode::Left
+        | KeyCode::Right
+        | KeyCode::Home
+        | KeyCode::End
+        | KeyCode::PageUp
+        | KeyCode::PageDown => moves,
+        KeyCode::Esc | KeyCode::F(_) => true,
+        KeyCode::Char(chr) if key.modifiers == KeyModifiers::CONTROL => "qfghjklcwbp".contains(chr),
+        KeyCode::Char(chr) if key.modifiers == KeyModifiers::ALT => ",.fgqpsw".contains(chr),
+        _ => false,
+    }
+}
+
 pub struct Editor {
-    reader: Reader,
     output: Output,
+    macros: MacroRegisters,
+    // macros being played, one inside the other
+    macro_depth: usize,
 }
 
 impl Editor {
     pub fn new() -> Self {
         terminal::enable_raw_mode().expect("Could not enable raw mode");
+        execute!(stdout(), event::EnableBracketedPaste).expect("Could not enable bracketed paste");
         // execute!(stdout(), cursor::Hide).expect("Could not hide cursor");
         Self {
-            reader: Reader,
             output: Output::new(),
+            macros: MacroRegisters::load(),
+            macro_depth: 0,
         }
     }
 
     fn process_keyevent(&mut self) -> crossterm::Result<bool> {
-        match self.reader.read_keyevent() {
+        let key_event = match self.output.reader.read_event() {
+            Some(Event::Paste(_)) if self.output.in_list() => return Ok(true),
+            Some(Event::Paste(text)) => {
+                self.output.paste(&text);
+                return Ok(true);
+            }
+            Some(Event::Key(event)) => Some(event),
+            _ => None,
+        };
+        // list buffers are read only, Enter opens the entry under the cursor
+        //  while Space and CTRL + s select and apply the changes of a preview
+        if self.output.in_list() {
+            match key_event {
+                Some(event::KeyEvent {
+                    code: KeyCode::Enter,
+                    ..
+                }) => {
+                    self.output.open_list_entry();
+                    return Ok(true);
+                }
+                Some(event::KeyEvent {
+                    code: KeyCode::Char(' '),
+                    modifiers: KeyModifiers::NONE,
+                    ..
+                }) => {
+                    self.output.toggle_list_entry();
+                    return Ok(true);
+                }
+                Some(event::KeyEvent {
+                    code: KeyCode::Char('s'),
+                    modifiers: KeyModifiers::CONTROL,
+                    ..
+                }) => {
+                    self.output.apply_replace();
+                    return Ok(true);
+                }
+                Some(key) if !is_read_only(&key) => return Ok(true),
+                _ => (),
+            }
+        }
+        match key_event {
 
 
             /* exit the program */
@@ -42,7 +126,7 @@ impl Editor {
                 modifiers: event::KeyModifiers::CONTROL,
                 ..
             }) => {
-                if self.output.dirty > 0 {
+                if self.output.is_modified() {
                     self.output.status_message.set_message(
                         "Modified! CTRL + q to exit or ESC to cancel"
                             .to_string(),
@@ -75,6 +159,31 @@ impl Editor {
                 modifiers: KeyModifiers::NONE,
                 ..
             }) => self.output.move_cursor(direction),
+            Some(event::KeyEvent {
+                code:
+                    direction @ (KeyCode::Up
+                    | KeyCode::Down
+                    | KeyCode::Left
+                    | KeyCode::Right
+                    | KeyCode::Home
+                    | KeyCode::End
+                    | KeyCode::PageUp
+                    | KeyCode::PageDown),
+                modifiers: KeyModifiers::SHIFT,
+                ..
+            }) => self.output.select(direction),
+            Some(event::KeyEvent {
+                code: direction @ (KeyCode::Left | KeyCode::Right),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) => self.output.move_word(direction),
+            Some(event::KeyEvent {
+                code: direction @ (KeyCode::Left | KeyCode::Right),
+                modifiers,
+                ..
+            }) if modifiers == KeyModifiers::CONTROL | KeyModifiers::SHIFT => {
+                self.output.select_word(direction)
+            }
             Some(event::KeyEvent {
                 code:
                     direction @ (KeyCode::Char('h')
@@ -92,11 +201,13 @@ impl Editor {
                 modifiers: KeyModifiers::CONTROL,
                 ..
             }) => {
-                if matches!(self.output.editor_rows.filename, None) {
-                    self.output.editor_rows.filename =
-                        prompt!(&mut self.output, "Save as : {}").map(|it| it.into());
-                    if let None = self.output.editor_rows.filename {
-                        return Ok(true);
+                if self.output.editor_rows.filename.is_none() {
+                    match prompt!(&mut self.output, PromptKind::File, "Save as : {}") {
+                        Some(name) => self
+                            .output
+                            .editor_rows
+                            .set_filename(expand_tilde(&name), &self.output.config),
+                        None => return Ok(true),
                     }
                 }
                 self.output.save();
@@ -114,6 +225,17 @@ impl Editor {
                 modifiers: KeyModifiers::NONE,
                 ..
             }) => self.output.del(),
+            // most terminals send ALT + backspace for CTRL + backspace
+            Some(event::KeyEvent {
+                code: KeyCode::Backspace,
+                modifiers: KeyModifiers::CONTROL | KeyModifiers::ALT,
+                ..
+            }) => self.output.delete_word(KeyCode::Left),
+            Some(event::KeyEvent {
+                code: KeyCode::Delete,
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) => self.output.delete_word(KeyCode::Right),
 
 
             /* new line */
@@ -124,21 +246,198 @@ impl Editor {
             }) => self.output.enter(),
 
 
+            /* indentation */
+            Some(event::KeyEvent {
+                code: KeyCode::Tab,
+                modifiers: KeyModifiers::NONE,
+                ..
+            }) => self.output.tab(),
+            Some(event::KeyEvent {
+                code: KeyCode::BackTab,
+                ..
+            }) => self.output.back_tab(),
+
+
             /* editing document content */
             Some(event::KeyEvent {
-                code: code @ (KeyCode::Char(..) | KeyCode::Tab),
-                modifiers: case @ (KeyModifiers::NONE | KeyModifiers::SHIFT),
+                code: KeyCode::Char(char),
+                modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
                 ..
-            }) => self.output.insert_char(match code {
-                KeyCode::Tab => '\t',
-                KeyCode::Char(char) if matches!(case, KeyModifiers::NONE) => char,
-                KeyCode::Char(char) if matches!(case, KeyModifiers::SHIFT) => {
-                    char.to_ascii_uppercase()
-                }
-                _ => unimplemented!(),
-            }),
+            }) => self.output.insert_char(char),
 
             
+            /* alt w toggles visible whitespace */
+            Some(event::KeyEvent {
+                code: KeyCode::Char('w'),
+                modifiers: KeyModifiers::ALT,
+                ..
+            }) => self.output.toggle_whitespace(),
+
+
+            /* undo and redo */
+            Some(event::KeyEvent {
+                code: KeyCode::Char('z'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) => self.output.undo(),
+            Some(event::KeyEvent {
+                code: KeyCode::Char('y'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) => self.output.redo(),
+
+
+            /* line operations */
+            Some(event::KeyEvent {
+                code: direction @ (KeyCode::Up | KeyCode::Down),
+                modifiers: KeyModifiers::ALT,
+                ..
+            }) => self.output.move_lines(direction),
+            Some(event::KeyEvent {
+                code: KeyCode::Char('d'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) => self.output.duplicate_lines(),
+            Some(event::KeyEvent {
+                code: KeyCode::Char('k'),
+                modifiers: KeyModifiers::ALT,
+                ..
+            }) => self.output.delete_lines(),
+            Some(event::KeyEvent {
+                code: KeyCode::Char('j'),
+                modifiers: KeyModifiers::ALT,
+                ..
+            }) => self.output.join_lines(),
+            Some(event::KeyEvent {
+                code: KeyCode::Char('o'),
+                modifiers: KeyModifiers::ALT,
+                ..
+            }) => self.output.insert_blank_line(KeyCode::Down),
+            Some(event::KeyEvent {
+                code: KeyCode::Char('O'),
+                modifiers,
+                ..
+            }) if modifiers.contains(KeyModifiers::ALT) => self.output.insert_blank_line(KeyCode::Up),
+
+
+            /* comments, terminals send CTRL + / as CTRL + 7 */
+            Some(event::KeyEvent {
+                code: KeyCode::Char('/'),
+                modifiers: KeyModifiers::ALT,
+                ..
+            })
+            | Some(event::KeyEvent {
+                code: KeyCode::Char('7'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) => self.output.toggle_comment(),
+            Some(event::KeyEvent {
+                code: KeyCode::Char('?'),
+                modifiers,
+                ..
+            }) if modifiers.contains(KeyModifiers::ALT) => self.output.toggle_block_comment(),
+
+
+            /* multiple cursors */
+            Some(event::KeyEvent {
+                code: direction @ (KeyCode::Up | KeyCode::Down),
+                modifiers,
+                ..
+            }) if modifiers == KeyModifiers::CONTROL | KeyModifiers::ALT => {
+                self.output.add_cursor(direction)
+            }
+            Some(event::KeyEvent {
+                code: KeyCode::Char('n'),
+                modifiers: KeyModifiers::ALT,
+                ..
+            }) => self.output.add_cursor_at_next(),
+            Some(event::KeyEvent {
+                code: KeyCode::Char('a'),
+                modifiers: KeyModifiers::ALT,
+                ..
+            }) => self.output.add_cursors_at_matches(),
+            Some(event::KeyEvent {
+                code: KeyCode::Esc,
+                ..
+            }) => self.output.clear_cursors(),
+
+
+            /* block selection and clipboard */
+            Some(event::KeyEvent {
+                code: KeyCode::Char('b'),
+                modifiers: KeyModifiers::ALT,
+                ..
+            }) => self.output.toggle_block(),
+            Some(event::KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) => self.output.copy(),
+            Some(event::KeyEvent {
+                code: KeyCode::Char('x'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) => self.output.cut(),
+            Some(event::KeyEvent {
+                code: KeyCode::Char('v'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) => self.output.paste_clipboard(),
+            Some(event::KeyEvent {
+                code: KeyCode::Char('r'),
+                modifiers: KeyModifiers::ALT,
+                ..
+            }) => self.output.copy_to_register()?,
+            Some(event::KeyEvent {
+                code: KeyCode::Char('y'),
+                modifiers: KeyModifiers::ALT,
+                ..
+            }) => self.output.pick_register()?,
+
+
+            /* keyboard macros */
+            Some(event::KeyEvent {
+                code: KeyCode::Char('q'),
+                modifiers: KeyModifiers::ALT,
+                ..
+            }) => self.toggle_recording()?,
+            Some(event::KeyEvent {
+                code: KeyCode::Char('p'),
+                modifiers: KeyModifiers::ALT,
+                ..
+            }) => {
+                if let Some(input) = prompt!(
+                    &mut self.output,
+                    PromptKind::Macro,
+                    "Play macro: {} (register, then a count or * to repeat until a search fails)"
+                ) {
+                    return self.play_macro(&input);
+                }
+            }
+            Some(event::KeyEvent {
+                code: KeyCode::Char('s'),
+                modifiers: KeyModifiers::ALT,
+                ..
+            }) => match self.macros.save() {
+                Ok(count) => self
+                    .output
+                    .status_message
+                    .set_message(format!("{} macros saved", count)),
+                Err(err) => self
+                    .output
+                    .status_message
+                    .set_message(format!("Error saving macros {:?}", err.to_string())),
+            },
+
+
+            /* ctrl b jumps to the matching bracket */
+            Some(event::KeyEvent {
+                code: KeyCode::Char('b'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) => self.output.jump_to_bracket(),
+
+
             /* ctrl f to find */
             Some(event::KeyEvent {
                 code: KeyCode::Char('f'),
@@ -147,12 +446,156 @@ impl Editor {
             }) => {
                 self.output.find()?;
             }
+            Some(event::KeyEvent {
+                code: KeyCode::Char('r'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) => self.output.substitute()?,
+            Some(event::KeyEvent {
+                code: KeyCode::Char('f'),
+                modifiers: KeyModifiers::ALT,
+                ..
+            }) => self.output.grep()?,
+            Some(event::KeyEvent {
+                code: KeyCode::Char('F'),
+                modifiers,
+                ..
+            }) if modifiers.contains(KeyModifiers::ALT) => self.output.grep_replace()?,
+            Some(event::KeyEvent {
+                code: KeyCode::Char('p'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) => self.output.find_file()?,
+            // xterm's SHIFT + F3 reads as a cursor position report, CTRL + g and ALT + g stand in
+            Some(event::KeyEvent {
+                code: KeyCode::F(3),
+                modifiers,
+                ..
+            }) => self.output.search_next(modifiers.contains(KeyModifiers::SHIFT)),
+            Some(event::KeyEvent {
+                code: KeyCode::Char('g'),
+                modifiers: modifiers @ (KeyModifiers::CONTROL | KeyModifiers::ALT),
+                ..
+            }) => self.output.search_next(modifiers == KeyModifiers::ALT),
+
+
+            /* buffers */
+            Some(event::KeyEvent {
+                code: code @ (KeyCode::Char(',') | KeyCode::Char('.')),
+                modifiers: KeyModifiers::ALT,
+                ..
+            }) => self.output.switch_buffer(if code == KeyCode::Char(',') { KeyCode::Left } else { KeyCode::Right }),
+            Some(event::KeyEvent {
+                code: KeyCode::Char('w'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) => self.output.close_buffer(),
             Some(_) => (),
             None => (),
 
 
         }
-        return Ok(true);
+        Ok(true)
+    }
+
+    /* @brief starts recording a macro into the register named by the next
+     *        key, or stops the recording in progress and stores it
+     */
+    fn toggle_recording(&mut self) -> crossterm::Result<()> {
+        if let Some(register) = self.output.recording.take() {
+            let mut events = self.output.reader.stop_recording().unwrap_or_default();
+            // the ALT + q that stopped the recording
+            events.pop();
+            self.output
+                .status_message
+                .set_message(format!("Recorded {} keys into @{}", events.len(), register));
+            self.macros.set(register, events);
+            return Ok(());
+        }
+        self.output
+            .status_message
+            .set_message("Record macro into register: press a-z".into());
+        self.output.refresh()?;
+        let key = loop {
+            if let Some(event) = self.output.reader.read_event() {
+                break event;
+            }
+        };
+        match key {
+            Event::Key(event::KeyEvent {
+                code: KeyCode::Char(register @ 'a'..='z'),
+                modifiers: KeyModifiers::NONE,
+                ..
+            }) => {
+                self.output.reader.start_recording();
+                self.output.recording = Some(register);
+                self.output
+                    .status_message
+                    .set_message(format!("Recording @{} (ALT + q to stop)", register));
+            }
+            _ => self.output.status_message.set_message("Recording cancelled".into()),
+        }
+        Ok(())
+    }
+
+    /* @brief plays the macro named by input, a register optionally followed
+     *        by a repeat count or * to repeat until a search fails or a run
+     *        changes nothing, a failed search always stops playback,
+     *        returns false when the macro quit the editor
+     */
+    fn play_macro(&mut self, input: &str) -> crossterm::Result<bool> {
+        let mut chars = input.chars();
+        let register = chars.next().unwrap_or(' ');
+        let times = match chars.as_str().trim() {
+            "" => Some(1),
+            "*" => None,
+            count => match count.parse() {
+                Ok(count) => Some(count),
+                Err(_) => {
+                    self.output
+                        .status_message
+                        .set_message(format!("Invalid count {}", count));
+                    return Ok(true);
+                }
+            },
+        };
+        let Some(events) = self.macros.get(register).cloned() else {
+            self.output
+                .status_message
+                .set_message(format!("No macro in @{}", register));
+            return Ok(true);
+        };
+        if self.macro_depth >= MACRO_DEPTH_LIMIT {
+            self.output
+                .status_message
+                .set_message("Macros nested too deeply".into());
+            return Ok(true);
+        }
+        self.macro_depth += 1;
+        let mut result = Ok(true);
+        let mut count = 0;
+        while times.map_or(count < MACRO_REPEAT_LIMIT, |times| count < times) {
+            let before = (self.output.cursor_position(), self.output.dirty);
+            self.output.search_failed = false;
+            let rest = self.output.reader.queued();
+            self.output.reader.play(&events);
+            while self.output.reader.queued() > rest && result.as_ref().is_ok_and(|run| *run) {
+                result = self.process_keyevent();
+                if self.output.search_failed {
+                    break;
+                }
+            }
+            // drop what is left of this run, the macros playing it keep their events
+            let left = self.output.reader.queued().saturating_sub(rest);
+            self.output.reader.skip(left);
+            count += 1;
+            let unchanged = before == (self.output.cursor_position(), self.output.dirty);
+            if self.output.search_failed || !result.as_ref().is_ok_and(|run| *run) || (times.is_none() && unchanged) {
+                break;
+            }
+        }
+        self.macro_depth -= 1;
+        result
     }
 
     pub fn execute(&mut self) -> crossterm::Result<bool> {
@@ -0,0 +1,324 @@
+use std::{fs, path::Path};
+
+use crate::config::IndentOverrides;
+use crate::file_format::{Charset, FileFormat, LineEnding};
+
+/* @brief properties .editorconfig files give a single file, None for the
+ *        ones none of the matching sections set
+ */
+#[derive(Default)]
+pub struct EditorConfig {
+    indent_style: Option<String>,
+    indent_size: Option<String>,
+    tab_width: Option<usize>,
+    end_of_line: Option<LineEnding>,
+    charset: Option<Charset>,
+    trim_trailing_whitespace: Option<bool>,
+    insert_final_newline: Option<bool>,
+}
+
+impl EditorConfig {
+    /* @brief reads the .editorconfig files from the directory of file up to
+     *        the first one marked root, sections closer to the file and
+     *        later in a file take precedence
+     */
+    pub fn for_file(file: &Path) -> Self {
+        let mut config = Self::default();
+        let Ok(file) = file.canonicalize() else {
+            return config;
+        };
+        let mut found = Vec::new();
+        for dir in file.ancestors().skip(1) {
+            if let Ok(content) = fs::read_to_string(dir.join(".editorconfig")) {
+                let is_root = parse(&content).0;
+                found.push((dir.to_path_buf(), content));
+                if is_root {
+                    break;
+                }
+            }
+        }
+        for (dir, content) in found.iter().rev() {
+            let Ok(relative) = file.strip_prefix(dir) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            for (pattern, properties) in parse(content).1 {
+                if section_matches(&pattern, &relative) {
+                    properties
+                        .iter()
+                        .for_each(|(key, value)| config.set(key, value));
+                }
+            }
+        }
+        config
+    }
+
+    // property names and values are case insensitive, unknown ones are ignored
+    fn set(&mut self, key: &str, value: &str) {
+        let value = value.to_lowercase();
+        match key {
+            "indent_style" => self.indent_style = Some(value),
+            "indent_size" => self.indent_size = Some(value),
+            "tab_width" => self.tab_width = value.parse().ok().filter(|width| *width > 0),
+            "end_of_line" => self.end_of_line = LineEnding::from_name(&value),
+            "charset" => self.charset = Charset::from_name(&value),
+            "trim_trailing_whitespace" => self.trim_trailing_whitespace = value.parse().ok(),
+            "insert_final_newline" => self.insert_final_newline = value.parse().ok(),
+            _ => (),
+        }
+    }
+
+    /* @brief indentation settings, indent_size = tab means a level is one
+     *        tab wide and tab_width defaults to a numeric indent_size
+     */
+    pub fn indent(&self) -> IndentOverrides {
+        let indent_size = self
+            .indent_size
+            .as_ref()
+            .and_then(|size| size.parse().ok())
+            .filter(|size| *size > 0);
+        IndentOverrides {
+            tab_width: self.tab_width.or(indent_size),
+            indent_width: if self.indent_size.as_deref() == Some("tab") {
+                self.tab_width
+            } else {
+                indent_size
+            },
+            expand_tabs: match self.indent_style.as_deref() {
+                Some("space") => Some(true),
+                Some("tab") => Some(false),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn apply_format(&self, format: &mut FileFormat) {
+        if let Some(line_ending) = self.end_of_line {
+            format.line_ending = line_ending;
+        }
+        if let Some(charset) = self.charset {
+            format.charset = charset;
+        }
+        if let Some(trim) = self.trim_trailing_whitespace {
+            format.trim_trailing_whitespace = trim;
+        }
+        if let Some(final_newline) = self.insert_final_newline {
+            format.final_newline = final_newline;
+        }
+    }
+}
+
+type Section = (String, Vec<(String, String)>);
+
+/* @brief splits an .editorconfig into whether it is marked root and its
+ *        sections, each a glob with its key value pairs
+ */
+fn parse(content: &str) -> (bool, Vec<Section>) {
+    let mut root = false;
+    let mut sections: Vec<Section> = Vec::new();
+    for line in content.lines().map(|line| line.trim()) {
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(pattern) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            sections.push((pattern.to_string(), Vec::new()));
+        } else if let Some((key, value)) = line.split_once('=') {
+            let key = key.trim().to_lowercase();
+            let value = value.trim().to_string();
+            match sections.last_mut() {
+                Some((_, properties)) => properties.push((key, value)),
+                None if key == "root" => root = value.eq_ignore_ascii_case("true"),
+                None => (),
+            }
+        }
+    }
+    (root, sections)
+}
+
+/* @brief a pattern without a / matches the file name in any directory,
+ *        otherwise it matches the path relative to the .editorconfig
+ */
+fn section_matches(pattern: &str, relative: &str) -> bool {
+    let pattern = match pattern.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if pattern.contains('/') => pattern.to_string(),
+        None => format!("**/{}", pattern),
+    };
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = relative.chars().collect();
+    glob_match(&pattern, &text)
+        || (pattern.starts_with(&['*', '*', '/']) && glob_match(&pattern[3..], &text))
+}
+
+/* @brief matches the editorconfig glob syntax, * and ? don't cross a /
+ *        while ** does, [abc] [!a-z] {one,two} and {1..10} are supported
+ */
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            (0..=text.len()).any(|skip| glob_match(&pattern[2..], &text[skip..]))
+        }
+        Some('*') => (0..=text.len())
+            .take_while(|skip| *skip == 0 || text[skip - 1] != '/')
+            .any(|skip| glob_match(&pattern[1..], &text[skip..])),
+        Some('?') => {
+            text.first().is_some_and(|chr| *chr != '/') && glob_match(&pattern[1..], &text[1..])
+        }
+        Some('[') => match class_match(&pattern[1..], text.first().copied()) {
+            Some((matched, len)) => matched && glob_match(&pattern[len + 1..], &text[1..]),
+            None => literal_match(pattern, text),
+        },
+        Some('{') => match brace_alternatives(&pattern[1..]) {
+            Some((alternatives, len)) => {
+                let rest = &pattern[len + 1..];
+                alternatives.iter().any(|alternative| match alternative {
+                    Alternative::Range(from, to) => (1..=text.len()).any(|len| {
+                        let number: String = text[..len].iter().collect();
+                        number
+                            .parse::<i64>()
+                            .is_ok_and(|number| number >= *from && number <= *to)
+                            && glob_match(rest, &text[len..])
+                    }),
+                    Alternative::Pattern(alternative) => {
+                        glob_match(&[alternative.as_slice(), rest].concat(), text)
+                    }
+                })
+            }
+            None => literal_match(pattern, text),
+        },
+        Some('\\') if pattern.len() > 1 => literal_match(&pattern[1..], text),
+        Some(_) => literal_match(pattern, text),
+    }
+}
+
+fn literal_match(pattern: &[char], text: &[char]) -> bool {
+    text.first() == pattern.first() && glob_match(&pattern[1..], &text[1..])
+}
+
+/* @brief matches chr against the class starting after a [, returning
+ *        whether it matched and the length of the class up to the ]
+ */
+fn class_match(class: &[char], chr: Option<char>) -> Option<(bool, usize)> {
+    let end = class.iter().skip(1).position(|it| *it == ']')? + 1;
+    let (negated, items) = match class[..end].strip_prefix(&['!']) {
+        Some(items) => (true, items),
+        None => (false, &class[..end]),
+    };
+    let Some(chr) = chr.filter(|chr| *chr != '/') else {
+        return Some((false, end + 1));
+    };
+    let mut matched = false;
+    let mut idx = 0;
+    while idx < items.len() {
+        if idx + 2 < items.len() && items[idx + 1] == '-' {
+            matched |= items[idx] <= chr && chr <= items[idx + 2];
+            idx += 3;
+        } else {
+            matched |= items[idx] == chr;
+            idx += 1;
+        }
+    }
+    Some((matched != negated, end + 1))
+}
+
+enum Alternative {
+    Pattern(Vec<char>),
+    Range(i64, i64),
+}
+
+/* @brief splits the braces starting after a { on top level commas,
+ *        returning the alternatives and the length up to the }
+ */
+fn brace_alternatives(braces: &[char]) -> Option<(Vec<Alternative>, usize)> {
+    let mut depth = 0;
+    let mut alternatives = Vec::new();
+    let mut start = 0;
+    for (idx, chr) in braces.iter().enumerate() {
+        match chr {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            '}' => {
+                alternatives.push(braces[start..idx].to_vec());
+                let inner: String = braces[..idx].iter().collect();
+                if let Some((from, to)) = inner.split_once("..") {
+                    if let (Ok(from), Ok(to)) = (from.parse(), to.parse()) {
+                        return Some((vec![Alternative::Range(from, to)], idx + 1));
+                    }
+                }
+                // a single alternative isn't a brace expansion, {word} is literal
+                if alternatives.len() < 2 {
+                    return None;
+                }
+                return Some((
+                    alternatives.into_iter().map(Alternative::Pattern).collect(),
+                    idx + 1,
+                ));
+            }
+            ',' if depth == 0 => {
+                alternatives.push(braces[start..idx].to_vec());
+                start = idx + 1;
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(pattern: &str, text: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        glob_match(&pattern, &text)
+    }
+
+    #[test]
+    fn stars_and_question_marks() {
+        assert!(glob("*.rs", "main.rs"));
+        assert!(!glob("*.rs", "src/main.rs"));
+        assert!(glob("**.rs", "src/main.rs"));
+        assert!(glob("src/**/mod.rs", "src/a/b/mod.rs"));
+        assert!(glob("?.c", "a.c"));
+        assert!(!glob("?.c", "ab.c"));
+        assert!(!glob("a?b", "a/b"));
+    }
+
+    #[test]
+    fn classes() {
+        assert!(glob("[abc].txt", "b.txt"));
+        assert!(!glob("[abc].txt", "d.txt"));
+        assert!(glob("[!a-c].txt", "d.txt"));
+        assert!(!glob("[!a-c].txt", "b.txt"));
+        assert!(glob("[a-z]1", "q1"));
+        // an unclosed class is taken literally
+        assert!(glob("[a", "[a"));
+    }
+
+    #[test]
+    fn braces() {
+        assert!(glob("*.{js,ts}", "app.ts"));
+        assert!(!glob("*.{js,ts}", "app.rs"));
+        assert!(glob("file{1..10}.txt", "file7.txt"));
+        assert!(!glob("file{1..10}.txt", "file11.txt"));
+        assert!(glob("{a,b/c}.md", "b/c.md"));
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(glob("\\*.md", "*.md"));
+        assert!(!glob("\\*.md", "a.md"));
+    }
+
+    #[test]
+    fn sections_match_in_any_directory_unless_they_have_a_slash() {
+        assert!(section_matches("*.py", "tools/build.py"));
+        assert!(section_matches("*.py", "build.py"));
+        assert!(section_matches("/build.py", "build.py"));
+        assert!(!section_matches("/build.py", "tools/build.py"));
+        assert!(section_matches("tools/*.py", "tools/build.py"));
+        assert!(!section_matches("tools/*.py", "src/tools/build.py"));
+    }
+}
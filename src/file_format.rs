@@ -0,0 +1,165 @@
+/* @brief how a buffer's rows are turned into bytes on disk and back
+ */
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl LineEnding {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lf" => Some(LineEnding::Lf),
+            "crlf" => Some(LineEnding::CrLf),
+            "cr" => Some(LineEnding::Cr),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    // the ending used by the first line break in text, Lf if there is none
+    fn detect(text: &str) -> Self {
+        match text.find(['\r', '\n']) {
+            Some(idx) if text[idx..].starts_with("\r\n") => LineEnding::CrLf,
+            Some(idx) if text[idx..].starts_with('\r') => LineEnding::Cr,
+            _ => LineEnding::Lf,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Charset {
+    Utf8,
+    Utf8Bom,
+    Latin1,
+    Utf16Be,
+    Utf16Le,
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+impl Charset {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "utf-8" => Some(Charset::Utf8),
+            "utf-8-bom" => Some(Charset::Utf8Bom),
+            "latin1" => Some(Charset::Latin1),
+            "utf-16be" => Some(Charset::Utf16Be),
+            "utf-16le" => Some(Charset::Utf16Le),
+            _ => None,
+        }
+    }
+
+    /* @brief decodes bytes read from disk, anything that isn't valid in
+     *        the charset is replaced with U+FFFD
+     */
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        match self {
+            Charset::Utf8 | Charset::Utf8Bom => {
+                String::from_utf8_lossy(bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes)).into_owned()
+            }
+            Charset::Latin1 => bytes.iter().map(|byte| *byte as char).collect(),
+            Charset::Utf16Be | Charset::Utf16Le => {
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|pair| {
+                        if *self == Charset::Utf16Be {
+                            u16::from_be_bytes([pair[0], pair[1]])
+                        } else {
+                            u16::from_le_bytes([pair[0], pair[1]])
+                        }
+                    })
+                    .collect();
+                let units = units.strip_prefix(&[0xFEFF]).unwrap_or(&units);
+                String::from_utf16_lossy(units)
+            }
+        }
+    }
+
+    // characters latin1 can't hold are written as ?
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        match self {
+            Charset::Utf8 => text.as_bytes().to_vec(),
+            Charset::Utf8Bom => [UTF8_BOM, text.as_bytes()].concat(),
+            Charset::Latin1 => text
+                .chars()
+                .map(|chr| u8::try_from(chr).unwrap_or(b'?'))
+                .collect(),
+            Charset::Utf16Be => text.encode_utf16().flat_map(|unit| unit.to_be_bytes()).collect(),
+            Charset::Utf16Le => text.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect(),
+        }
+    }
+}
+
+/* @brief the on disk format of a buffer, taken from the file when it is
+ *        loaded and overridden by the config files and .editorconfig
+ */
+#[derive(Clone, Copy)]
+pub struct FileFormat {
+    pub line_ending: LineEnding,
+    pub charset: Charset,
+    // save time clean up of the rows
+    pub trim_trailing_whitespace: bool,
+    pub trim_trailing_blank_lines: bool,
+    pub final_newline: bool,
+}
+
+impl Default for FileFormat {
+    fn default() -> Self {
+        Self {
+            line_ending: LineEnding::Lf,
+            charset: Charset::Utf8,
+            trim_trailing_whitespace: false,
+            trim_trailing_blank_lines: false,
+            final_newline: true,
+        }
+    }
+}
+
+impl FileFormat {
+    /* @brief the format bytes were written in, a byte order mark picks the
+     *        charset and the first line break picks the line ending
+     */
+    pub fn detect(bytes: &[u8]) -> Self {
+        let charset = if bytes.starts_with(UTF8_BOM) {
+            Charset::Utf8Bom
+        } else if bytes.starts_with(&[0xFE, 0xFF]) {
+            Charset::Utf16Be
+        } else if bytes.starts_with(&[0xFF, 0xFE]) {
+            Charset::Utf16Le
+        } else {
+            Charset::Utf8
+        };
+        let text = charset.decode(bytes);
+        Self {
+            line_ending: LineEnding::detect(&text),
+            charset,
+            trim_trailing_whitespace: false,
+            trim_trailing_blank_lines: false,
+            final_newline: text.is_empty() || text.ends_with(['\n', '\r']),
+        }
+    }
+
+    // splits decoded text into rows on any of the line endings
+    pub fn split_rows(text: &str) -> Vec<String> {
+        let mut rows: Vec<String> = text
+            .split("\r\n")
+            .flat_map(|part| part.split(['\r', '\n']))
+            .map(String::from)
+            .collect();
+        // a final line ending doesn't start another row
+        if text.ends_with(['\n', '\r']) || text.is_empty() {
+            rows.pop();
+        }
+        rows
+    }
+}
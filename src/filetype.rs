@@ -0,0 +1,213 @@
+use std::path::Path;
+
+/* @brief language specific editing behaviour, picked by file extension
+ */
+pub struct FileType {
+    pub name: &'static str,
+    // a line ending in one of these opens a new indentation level
+    pub indent_after: &'static [char],
+    // opener closer pairs typed together, written one after the other
+    pub auto_pairs: &'static str,
+    // starts a comment running to the end of the line
+    pub line_comment: Option<&'static str>,
+    // start and end of a comment that can span lines
+    pub block_comment: Option<(&'static str, &'static str)>,
+}
+
+const C_LIKE: &[char] = &['{', '(', '['];
+const BRACKETS: &str = "()[]{}";
+const BRACKETS_DOUBLE: &str = "()[]{}\"\"";
+const BRACKETS_QUOTES: &str = "()[]{}\"\"''";
+
+const C_BLOCK: Option<(&str, &str)> = Some(("/*", "*/"));
+const HTML_BLOCK: Option<(&str, &str)> = Some(("<!--", "-->"));
+
+const FILETYPES: &[(&[&str], FileType)] = &[
+    (
+        &["rs"],
+        FileType {
+            name: "rust",
+            indent_after: C_LIKE,
+            auto_pairs: BRACKETS_DOUBLE,
+            line_comment: Some("//"),
+            block_comment: C_BLOCK,
+        },
+    ),
+    (
+        &["c", "h", "cc", "cpp", "hpp", "cxx"],
+        FileType {
+            name: "c",
+            indent_after: C_LIKE,
+            auto_pairs: BRACKETS_QUOTES,
+            line_comment: Some("//"),
+            block_comment: C_BLOCK,
+        },
+    ),
+    (
+        &["js", "jsx", "ts", "tsx", "mjs"],
+        FileType {
+            name: "javascript",
+            indent_after: C_LIKE,
+            auto_pairs: "()[]{}\"\"''``",
+            line_comment: Some("//"),
+            block_comment: C_BLOCK,
+        },
+    ),
+    (
+        &["java", "kt", "scala", "cs", "swift"],
+        FileType {
+            name: "java",
+            indent_after: C_LIKE,
+            auto_pairs: BRACKETS_QUOTES,
+            line_comment: Some("//"),
+            block_comment: C_BLOCK,
+        },
+    ),
+    (
+        &["go"],
+        FileType {
+            name: "go",
+            indent_after: C_LIKE,
+            auto_pairs: "()[]{}\"\"``",
+            line_comment: Some("//"),
+            block_comment: C_BLOCK,
+        },
+    ),
+    (
+        &["json"],
+        FileType {
+            name: "json",
+            indent_after: &['{', '['],
+            auto_pairs: "[]{}\"\"",
+            line_comment: None,
+            block_comment: None,
+        },
+    ),
+    (
+        &["css", "scss"],
+        FileType {
+            name: "css",
+            indent_after: &['{'],
+            auto_pairs: BRACKETS_QUOTES,
+            line_comment: None,
+            block_comment: C_BLOCK,
+        },
+    ),
+    (
+        &["py", "pyw"],
+        FileType {
+            name: "python",
+            indent_after: &[':', '{', '(', '['],
+            auto_pairs: BRACKETS_QUOTES,
+            line_comment: Some("#"),
+            block_comment: None,
+        },
+    ),
+    (
+        &["sh", "bash", "zsh"],
+        FileType {
+            name: "shell",
+            indent_after: &['{', '('],
+            auto_pairs: BRACKETS_QUOTES,
+            line_comment: Some("#"),
+            block_comment: None,
+        },
+    ),
+    (
+        &["lua"],
+        FileType {
+            name: "lua",
+            indent_after: &['{', '('],
+            auto_pairs: BRACKETS_QUOTES,
+            line_comment: Some("--"),
+            block_comment: Some(("--[[", "]]")),
+        },
+    ),
+    (
+        &["toml", "ini"],
+        FileType {
+            name: "toml",
+            indent_after: &['[', '{'],
+            auto_pairs: BRACKETS_QUOTES,
+            line_comment: Some("#"),
+            block_comment: None,
+        },
+    ),
+    (
+        &["yaml", "yml"],
+        FileType {
+            name: "yaml",
+            indent_after: &[':'],
+            auto_pairs: BRACKETS_QUOTES,
+            line_comment: Some("#"),
+            block_comment: None,
+        },
+    ),
+    (
+        &["html", "htm", "xml"],
+        FileType {
+            name: "html",
+            indent_after: &[],
+            auto_pairs: "<>\"\"''",
+            line_comment: None,
+            block_comment: HTML_BLOCK,
+        },
+    ),
+    (
+        &["md", "markdown"],
+        FileType {
+            name: "markdown",
+            indent_after: &[],
+            auto_pairs: BRACKETS,
+            line_comment: None,
+            block_comment: HTML_BLOCK,
+        },
+    ),
+];
+
+const PLAIN_TEXT: FileType = FileType {
+    name: "text",
+    indent_after: &[],
+    auto_pairs: "",
+    line_comment: None,
+    block_comment: None,
+};
+
+impl FileType {
+    pub fn from_path(path: Option<&Path>) -> &'static FileType {
+        path.and_then(|path| path.extension())
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| {
+                FILETYPES
+                    .iter()
+                    .find(|(exts, _)| exts.contains(&ext))
+                    .map(|(_, filetype)| filetype)
+            })
+            .unwrap_or(&PLAIN_TEXT)
+    }
+
+    // the bracket closing an opener from indent_after
+    pub fn closer(&self, opener: char) -> Option<char> {
+        match opener {
+            '{' => Some('}'),
+            '(' => Some(')'),
+            '[' => Some(']'),
+            _ => None,
+        }
+        .filter(|_| self.indent_after.contains(&opener))
+    }
+
+    pub fn is_closer(&self, chr: char) -> bool {
+        self.indent_after
+            .iter()
+            .any(|opener| self.closer(*opener) == Some(chr))
+    }
+
+    /* @brief reads auto pairs written one after the other, e.g. ()[]"",
+     *        an odd character at the end is ignored
+     */
+    pub fn parse_pairs(pairs: &str) -> Vec<(char, char)> {
+        let chars: Vec<char> = pairs.chars().collect();
+        chars.chunks_exact(2).map(|pair| (pair[0], pair[1])).collect()
+    }
+}
@@ -0,0 +1,191 @@
+use std::path::PathBuf;
+
+// rows the list of matching files takes on screen at most
+pub const FINDER_HEIGHT: usize = 10;
+
+// points for every matched character
+const MATCH: i64 = 16;
+// extra points for a match right after the previous one
+const CONSECUTIVE: i64 = 16;
+// extra points for a match on the first character of a path segment
+const SEGMENT_START: i64 = 24;
+// extra points for a match starting a word inside a segment, after _ - . or a space or on a capital
+const WORD_START: i64 = 12;
+// extra points for a match in the file name rather than the directories
+const FILE_NAME: i64 = 8;
+// points lost for every character skipped between two matches
+const GAP: i64 = 1;
+
+/* @brief how well query matches path as a subsequence ignoring case, None
+ *        when it doesn't, the characters are lined up the way that scores
+ *        best rather than at their first occurrence
+ */
+pub fn score(query: &str, path: &str) -> Option<i64> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let path: Vec<char> = path.chars().collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let name_start = path.iter().rposition(|chr| *chr == '/').map_or(0, |at| at + 1);
+    let bonus: Vec<i64> = (0..path.len())
+        .map(|at| {
+            let prev = at.checked_sub(1).map(|prev| path[prev]);
+            let position = match prev {
+                None | Some('/') => SEGMENT_START,
+                Some('_' | '-' | '.' | ' ') => WORD_START,
+                Some(prev) if prev.is_lowercase() && path[at].is_uppercase() => WORD_START,
+                _ => 0,
+            };
+            position + if at >= name_start { FILE_NAME } else { 0 }
+        })
+        .collect();
+    let matches = |at: usize, chr: char| path[at].to_lowercase().eq(std::iter::once(chr));
+    // best[at] is the best score of the query so far with its last character matched at at
+    let mut best: Vec<Option<i64>> = (0..path.len())
+        .map(|at| matches(at, query[0]).then(|| MATCH + bonus[at] - at as i64 * GAP))
+        .collect();
+    for chr in query.iter().skip(1) {
+        let mut next = vec![None; path.len()];
+        // the best previous score minus the gap up to at, taken over matches before at - 1
+        let mut gapped: Option<i64> = None;
+        for at in 1..path.len() {
+            if at >= 2 {
+                if let Some(prev) = best[at - 2] {
+                    gapped = gapped.max(Some(prev + (at as i64 - 2) * GAP));
+                }
+            }
+            if !matches(at, *chr) {
+                continue;
+            }
+            let after_gap = gapped.map(|prev| prev - (at as i64 - 1) * GAP);
+            let consecutive = best[at - 1].map(|prev| prev + CONSECUTIVE);
+            next[at] = after_gap.max(consecutive).map(|prev| prev + MATCH + bonus[at]);
+        }
+        best = next;
+    }
+    best.into_iter().flatten().max()
+}
+
+/* @brief the files of the project narrowed down to the ones matching a
+ *        query, best first, with one of them selected
+ */
+pub struct Finder {
+    files: Vec<String>,
+    // indices into files, best match first
+    matches: Vec<usize>,
+    selected: usize,
+}
+
+impl Finder {
+    pub fn new(files: Vec<PathBuf>) -> Self {
+        let files: Vec<String> = files.iter().map(|file| file.to_string_lossy().replace('\\', "/")).collect();
+        Self {
+            matches: (0..files.len()).collect(),
+            files,
+            selected: 0,
+        }
+    }
+
+    // ranks the files again for query, ties go to the shorter path
+    pub fn update(&mut self, query: &str) {
+        let mut scored: Vec<(i64, usize)> = self
+            .files
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, file)| score(query, file).map(|score| (score, idx)))
+            .collect();
+        scored.sort_by_key(|(score, idx)| (-score, self.files[*idx].len(), *idx));
+        self.matches = scored.into_iter().map(|(_, idx)| idx).collect();
+        self.selected = 0;
+    }
+
+    // moves the selection down, or up when up is set, wrapping around
+    pub fn step(&mut self, up: bool) {
+        if self.matches.is_empty() {
+            return;
+        }
+        self.selected = if up {
+            self.selected.checked_sub(1).unwrap_or(self.matches.len() - 1)
+        } else {
+            (self.selected + 1) % self.matches.len()
+        };
+    }
+
+    pub fn selection(&self) -> Option<&str> {
+        self.matches.get(self.selected).map(|idx| self.files[*idx].as_str())
+    }
+
+    // how many files match out of all of them
+    pub fn counts(&self) -> (usize, usize) {
+        (self.matches.len(), self.files.len())
+    }
+
+    /* @brief at most height matches around the selection, each with whether
+     *        it is the selected one
+     */
+    pub fn lines(&self, height: usize) -> Vec<(String, bool)> {
+        let start = (self.selected + 1).saturating_sub(height);
+        self.matches
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(height)
+            .map(|(pos, idx)| (self.files[*idx].clone(), pos == self.selected))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_subsequences_ignoring_case() {
+        assert!(score("mn", "src/main.rs").is_some());
+        assert!(score("SRCmain", "src/main.rs").is_some());
+        assert_eq!(score("nm", "src/main.rs"), None);
+        assert_eq!(score("x", "src/main.rs"), None);
+        assert_eq!(score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn prefers_consecutive_matches() {
+        assert!(score("main", "src/main.rs") > score("main", "src/m_a_i_n.rs"));
+    }
+
+    #[test]
+    fn prefers_segment_and_word_starts() {
+        assert!(score("o", "src/output.rs") > score("o", "src/rows.rs"));
+        assert!(score("fr", "file_rows.rs") > score("fr", "fxxrxx.rs"));
+        assert!(score("fr", "fileRows.rs") > score("fr", "filerows.rs"));
+    }
+
+    #[test]
+    fn prefers_the_file_name_to_directories() {
+        assert!(score("edit", "editor/x.rs") < score("edit", "x/editor.rs"));
+    }
+
+    #[test]
+    fn lines_up_characters_where_they_score_best() {
+        // taking the first a would leave the same gap as in xaxxb
+        assert!(score("ab", "xaxab") > score("ab", "xaxxb"));
+        assert!(score("out", "docs/output.rs") > score("out", "docs/o_u_t.rs"));
+    }
+
+    #[test]
+    fn finder_ranks_and_steps_through_matches() {
+        let files = ["src/rows.rs", "src/output.rs", "README.md"].map(PathBuf::from).to_vec();
+        let mut finder = Finder::new(files);
+        assert_eq!(finder.counts(), (3, 3));
+        finder.update("rs");
+        assert_eq!(finder.selection(), Some("src/rows.rs"));
+        finder.step(false);
+        assert_eq!(finder.selection(), Some("src/output.rs"));
+        finder.step(false);
+        assert_eq!(finder.selection(), Some("src/rows.rs"));
+        finder.step(true);
+        assert_eq!(finder.selection(), Some("src/output.rs"));
+        finder.update("zzz");
+        assert_eq!((finder.counts(), finder.selection()), ((0, 3), None));
+    }
+}
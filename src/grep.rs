@@ -0,0 +1,432 @@
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+use regex::{Regex, RegexBuilder};
+
+use crate::buffers;
+use crate::rows::{self, EditorRows};
+use crate::search;
+
+// hits a project search collects at most
+const HIT_LIMIT: usize = 10_000;
+// bytes looked at for a NUL when telling binary files apart
+const BINARY_CHECK_LEN: usize = 8192;
+const BOM: &str = "\u{feff}";
+
+// a match in a file, line counts from 0 and start..end are bytes of the line
+#[derive(Clone)]
+pub struct Hit {
+    pub path: PathBuf,
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+// a hit of a project replace and what replaces it, applied while selected
+pub struct Change {
+    pub hit: Hit,
+    pub replacement: String,
+    pub selected: bool,
+}
+
+impl Change {
+    // the line of the hit once replaced
+    pub fn after(&self) -> String {
+        let text = &self.hit.text;
+        format!("{}{}{}", &text[..self.hit.start], self.replacement, &text[self.hit.end..])
+    }
+}
+
+/* @brief the files under root, skipping hidden ones and whatever
+ *        .gitignore, .ignore and the global git excludes leave out, also
+ *        outside of a git repository
+ */
+pub fn project_files(root: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = WalkBuilder::new(root)
+        .require_git(false)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|kind| kind.is_file()))
+        .map(|entry| {
+            let path = entry.into_path();
+            path.strip_prefix(root).map(Path::to_path_buf).unwrap_or(path)
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+// the contents of a text file, None for binary files and ones that aren't UTF-8
+pub fn read_text(path: &Path) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    if bytes[..bytes.len().min(BINARY_CHECK_LEN)].contains(&0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/* @brief the lines of text read by read_text, each with the line ending
+ *        it had, a BOM before the first one is left out like opening the
+ *        file leaves it out of the rows
+ */
+fn split_lines(text: &str) -> impl Iterator<Item = (&str, &str)> {
+    text.strip_prefix(BOM).unwrap_or(text).split_inclusive('\n').map(|line| {
+        let content = match line.strip_suffix('\n') {
+            Some(content) => content.strip_suffix('\r').unwrap_or(content),
+            None => line,
+        };
+        (content, &line[content.len()..])
+    })
+}
+
+/* @brief a pattern looked for line by line, literals ignore case through
+ *        Unicode case folding like the buffer search does
+ */
+pub enum Matcher {
+    Literal { pattern: String, ignore_case: bool },
+    Regex(Regex),
+}
+
+impl Matcher {
+    pub fn new(pattern: &str, regex: bool, ignore_case: bool) -> Result<Self, String> {
+        if !regex {
+            return Ok(Matcher::Literal {
+                pattern: pattern.to_string(),
+                ignore_case,
+            });
+        }
+        RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map(Matcher::Regex)
+            .map_err(|err| err.to_string().lines().last().unwrap_or_default().to_string())
+    }
+
+    pub fn find_all(&self, line: &str) -> Vec<Range<usize>> {
+        match self {
+            Matcher::Literal { pattern, ignore_case } => search::find_all(line, pattern, *ignore_case),
+            Matcher::Regex(regex) => regex
+                .find_iter(line)
+                .filter(|found| !found.is_empty())
+                .map(|found| found.range())
+                .collect(),
+        }
+    }
+
+    /* @brief what the match of hit is replaced with, $1 and ${name} in with
+     *        stand for the groups a regex captured
+     */
+    pub fn replacement(&self, hit: &Hit, with: &str) -> String {
+        match self {
+            Matcher::Literal { .. } => with.to_string(),
+            Matcher::Regex(regex) => {
+                let mut expanded = String::new();
+                if let Some(captures) = regex.captures_at(&hit.text, hit.start) {
+                    captures.expand(with, &mut expanded);
+                }
+                expanded
+            }
+        }
+    }
+}
+
+/* @brief every match of matcher in the text files under root, in file
+ *        order and stopping at HIT_LIMIT, returns whether it stopped early,
+ *        files open in one of open are searched in their rows with the
+ *        edits that weren't saved yet
+ */
+pub fn search(root: &Path, matcher: &Matcher, open: &[&EditorRows]) -> (Vec<Hit>, bool) {
+    let mut hits = Vec::new();
+    for path in project_files(root) {
+        let full = root.join(&path);
+        let buffer = open.iter().find(|rows| {
+            rows.filename.as_deref().is_some_and(|name| {
+                name.file_name() == full.file_name() && buffers::same_file(name, &full)
+            })
+        });
+        let text;
+        let lines: Vec<&str> = match buffer {
+            Some(rows) => (0..rows.num_rows()).map(|at| rows.get_row(at).row_content.as_str()).collect(),
+            None => {
+                let Some(read) = read_text(&full) else {
+                    continue;
+                };
+                text = read;
+                split_lines(&text).map(|(content, _)| content).collect()
+            }
+        };
+        for (line, content) in lines.into_iter().enumerate() {
+            for found in matcher.find_all(content) {
+                if hits.len() == HIT_LIMIT {
+                    return (hits, true);
+                }
+                hits.push(Hit {
+                    path: path.clone(),
+                    line,
+                    start: found.start,
+                    end: found.end,
+                    text: content.to_string(),
+                });
+            }
+        }
+    }
+    (hits, false)
+}
+
+/* @brief applies the changes of one file, in the order of its lines, to
+ *        its rows, a line that no longer reads as it did when searched is
+ *        left alone, returns how many changes were applied
+ */
+pub fn replace_rows(editor_rows: &mut EditorRows, changes: &[&Change]) -> usize {
+    let mut applied = 0;
+    for line in changes.chunk_by(|a, b| a.hit.line == b.hit.line) {
+        let at = line[0].hit.line;
+        if at >= editor_rows.num_rows() || editor_rows.get_row(at).row_content != line[0].hit.text {
+            continue;
+        }
+        // from the last match back, so the earlier ones stay where they were
+        for change in line.iter().rev() {
+            editor_rows.insert_str(at, change.hit.end, &change.replacement);
+            editor_rows.delete_range(at, change.hit.start, change.hit.end);
+        }
+        applied += line.len();
+    }
+    applied
+}
+
+// the line text with the matches of changes on it replaced
+fn splice(text: &str, changes: &[&Change]) -> String {
+    let mut spliced = String::new();
+    let mut from = 0;
+    for change in changes {
+        spliced.push_str(&text[from..change.hit.start]);
+        spliced.push_str(&change.replacement);
+        from = change.hit.end;
+    }
+    spliced.push_str(&text[from..]);
+    spliced
+}
+
+/* @brief applies the changes of one file to text read by read_text like
+ *        replace_rows does to rows, the bytes outside of the matches stay
+ *        as they were, returns the new text and how many were applied
+ */
+pub fn replace_text(text: &str, changes: &[&Change]) -> (String, usize) {
+    let mut replaced = String::with_capacity(text.len());
+    if text.starts_with(BOM) {
+        replaced.push_str(BOM);
+    }
+    let mut lines = changes.chunk_by(|a, b| a.hit.line == b.hit.line).peekable();
+    let mut applied = 0;
+    for (at, (content, ending)) in split_lines(text).enumerate() {
+        match lines.next_if(|line| line[0].hit.line == at) {
+            Some(line) if line[0].hit.text == content => {
+                replaced.push_str(&splice(content, line));
+                applied += line.len();
+            }
+            _ => replaced.push_str(content),
+        }
+        replaced.push_str(ending);
+    }
+    (replaced, applied)
+}
+
+/* @brief applies changes to a file that isn't open and rewrites it when
+ *        any was applied, without going through the rows and the format
+ *        saving them would apply
+ */
+pub fn replace_on_disk(path: &Path, changes: &[&Change]) -> io::Result<usize> {
+    let Some(text) = read_text(path) else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a UTF-8 text file"));
+    };
+    let (replaced, count) = replace_text(&text, changes);
+    if count > 0 {
+        rows::write_atomic(path, replaced.as_bytes())?;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::env;
+
+    // a directory only this test writes to, so tests running at once don't clash
+    fn scratch_dir(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("text_editor_grep_{}_{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    // a project with ignored, hidden, binary and Latin-1 files besides text ones
+    fn project(name: &str) -> PathBuf {
+        let root = scratch_dir(name);
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(root.join(".hidden"), "needle\n").unwrap();
+        fs::write(root.join("notes.log"), "needle\n").unwrap();
+        fs::write(root.join("target/out.txt"), "needle\n").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n// needle\n").unwrap();
+        fs::write(root.join("readme.txt"), "a needle, another needle\n").unwrap();
+        fs::write(root.join("image.bin"), b"needle\0\x89PNG").unwrap();
+        fs::write(root.join("latin1.txt"), b"caf\xe9 needle\n").unwrap();
+        root
+    }
+
+    #[test]
+    fn project_files_skip_ignored_and_hidden_files() {
+        let root = project("files");
+        let files = project_files(&root);
+        assert_eq!(files, ["image.bin", "latin1.txt", "readme.txt", "src/main.rs"].map(PathBuf::from));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn read_text_skips_binary_and_non_utf8_files() {
+        let root = project("read");
+        assert_eq!(read_text(&root.join("readme.txt")).as_deref(), Some("a needle, another needle\n"));
+        assert_eq!(read_text(&root.join("image.bin")), None);
+        assert_eq!(read_text(&root.join("latin1.txt")), None);
+        assert_eq!(read_text(&root.join("missing.txt")), None);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn regexes_never_match_nothing() {
+        let matcher = Matcher::new("x*", true, false).unwrap();
+        assert_eq!(matcher.find_all("axxbx"), vec![1..3, 4..5]);
+        assert!(matcher.find_all("abc").is_empty());
+        let matcher = Matcher::new("^", true, false).unwrap();
+        assert!(matcher.find_all("abc").is_empty());
+        let matcher = Matcher::new("STRASSE", false, true).unwrap();
+        assert_eq!(matcher.find_all("die Straße"), vec![4..11]);
+        assert!(Matcher::new("(", true, false).is_err());
+    }
+
+    #[test]
+    fn search_finds_matches_in_text_files_only() {
+        let root = project("search");
+        let matcher = Matcher::new("needle", false, false).unwrap();
+        let (hits, stopped) = search(&root, &matcher, &[]);
+        assert!(!stopped);
+        let found: Vec<_> = hits
+            .iter()
+            .map(|hit| (hit.path.to_str().unwrap(), hit.line, hit.start, hit.end))
+            .collect();
+        assert_eq!(found, [("readme.txt", 0, 2, 8), ("readme.txt", 0, 18, 24), ("src/main.rs", 1, 3, 9)]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn search_stops_at_the_hit_limit() {
+        let root = scratch_dir("limit");
+        fs::write(root.join("a.txt"), "x".repeat(HIT_LIMIT - 1)).unwrap();
+        fs::write(root.join("b.txt"), "xx\n").unwrap();
+        let matcher = Matcher::new("x", false, false).unwrap();
+        let (hits, stopped) = search(&root, &matcher, &[]);
+        assert!(stopped);
+        assert_eq!(hits.len(), HIT_LIMIT);
+        assert_eq!(hits.last().map(|hit| (hit.path.clone(), hit.start)), Some(("b.txt".into(), 0)));
+        fs::write(root.join("b.txt"), "x\n").unwrap();
+        let (hits, stopped) = search(&root, &matcher, &[]);
+        assert!(!stopped);
+        assert_eq!(hits.len(), HIT_LIMIT);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    fn change(line: usize, text: &str, found: Range<usize>, replacement: &str) -> Change {
+        Change {
+            hit: Hit {
+                path: PathBuf::from("file.txt"),
+                line,
+                start: found.start,
+                end: found.end,
+                text: text.to_string(),
+            },
+            replacement: replacement.to_string(),
+            selected: true,
+        }
+    }
+
+    #[test]
+    fn replacements_expand_regex_groups() {
+        let matcher = Matcher::new(r"(\w+)=(?<value>\d+)", true, false).unwrap();
+        let hit = change(0, "a=1, bb=22", 5..10, "").hit;
+        assert_eq!(matcher.replacement(&hit, "$2:$1"), "22:bb");
+        assert_eq!(matcher.replacement(&hit, "${value}_${1}"), "22_bb");
+        assert_eq!(matcher.replacement(&hit, "$$1"), "$1");
+        let literal = Matcher::new("a=1", false, false).unwrap();
+        assert_eq!(literal.replacement(&hit, "$1"), "$1");
+    }
+
+    #[test]
+    fn replace_rows_skips_lines_changed_since_the_search() {
+        let mut editor_rows = EditorRows::scratch(
+            vec!["one two two".into(), "edited".into(), "two".into()],
+            &Config::default(),
+        );
+        let changes = [
+            change(0, "one two two", 4..7, "2"),
+            change(0, "one two two", 8..11, "3"),
+            change(1, "two", 0..3, "x"),
+            change(2, "two", 0..3, "ii"),
+            change(5, "two", 0..3, "x"),
+        ];
+        let changes: Vec<&Change> = changes.iter().collect();
+        assert_eq!(replace_rows(&mut editor_rows, &changes), 3);
+        assert_eq!(editor_rows.row_contents(), ["one 2 3", "edited", "ii"]);
+    }
+
+    #[test]
+    fn replace_text_keeps_line_endings_and_the_bom() {
+        let text = "\u{feff}one two\r\nkept\rtwo\nstale\ntwo";
+        let changes = [
+            change(0, "one two", 4..7, "2"),
+            change(1, "kept\rtwo", 5..8, "2"),
+            change(2, "changed", 0..5, "x"),
+            change(3, "two", 0..3, "2"),
+        ];
+        let changes: Vec<&Change> = changes.iter().collect();
+        let (replaced, applied) = replace_text(text, &changes);
+        assert_eq!(replaced, "\u{feff}one 2\r\nkept\r2\nstale\n2");
+        assert_eq!(applied, 3);
+    }
+
+    #[test]
+    fn replace_on_disk_rewrites_only_the_matches() {
+        let root = scratch_dir("disk");
+        let path = root.join("file.txt");
+        fs::write(&path, "\u{feff}a  b\r\nb\t \n").unwrap();
+        let changes = [change(1, "b\t ", 0..1, "c")];
+        let changes: Vec<&Change> = changes.iter().collect();
+        assert_eq!(replace_on_disk(&path, &changes).unwrap(), 1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "\u{feff}a  b\r\nc\t \n");
+        assert!(replace_on_disk(&root.join("image.bin"), &changes).is_err());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn search_leaves_the_bom_out_and_reads_open_buffers() {
+        let root = scratch_dir("open");
+        fs::write(root.join("bom.txt"), "\u{feff}needle\n").unwrap();
+        fs::write(root.join("open.txt"), "needle on disk\n").unwrap();
+        let matcher = Matcher::new("needle", false, false).unwrap();
+        let config = Config::default();
+        let mut open = EditorRows::scratch(vec!["edited".into(), "a needle".into()], &config);
+        open.filename = Some(root.join("open.txt"));
+        let (hits, _) = search(&root, &matcher, &[&open]);
+        let found: Vec<_> = hits
+            .iter()
+            .map(|hit| (hit.path.to_str().unwrap(), hit.line, hit.start, hit.text.as_str()))
+            .collect();
+        assert_eq!(found, [("bom.txt", 0, 0, "needle"), ("open.txt", 1, 2, "a needle")]);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::config::config_dir;
+
+// entries kept per prompt kind, oldest are dropped first
+const HISTORY_LIMIT: usize = 100;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PromptKind {
+    Search,
+    File,
+    Macro,
+    Replace,
+    Grep,
+    GrepReplace,
+    FindFile,
+}
+
+impl PromptKind {
+    fn name(&self) -> &'static str {
+        match self {
+            PromptKind::Search => "search",
+            PromptKind::File => "file",
+            PromptKind::Macro => "macro",
+            PromptKind::Replace => "replace",
+            PromptKind::Grep => "grep",
+            PromptKind::GrepReplace => "grep-replace",
+            PromptKind::FindFile => "find-file",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "search" => Some(PromptKind::Search),
+            "file" => Some(PromptKind::File),
+            "macro" => Some(PromptKind::Macro),
+            "replace" => Some(PromptKind::Replace),
+            "grep" => Some(PromptKind::Grep),
+            "grep-replace" => Some(PromptKind::GrepReplace),
+            "find-file" => Some(PromptKind::FindFile),
+            _ => None,
+        }
+    }
+}
+
+/* @brief previously accepted prompt inputs, stored oldest to newest for each
+ *        kind of prompt and persisted as `kind<TAB>entry` lines
+ */
+pub struct PromptHistory {
+    entries: HashMap<PromptKind, Vec<String>>,
+    path: Option<PathBuf>,
+}
+
+impl PromptHistory {
+    pub fn load() -> Self {
+        let path = config_dir().map(|dir| dir.join("history"));
+        let mut history = Self {
+            entries: HashMap::new(),
+            path: None,
+        };
+        if let Some(content) = path.as_ref().and_then(|path| fs::read_to_string(path).ok()) {
+            content
+                .lines()
+                .filter_map(|line| line.split_once('\t'))
+                .filter_map(|(kind, entry)| PromptKind::from_name(kind).map(|kind| (kind, entry)))
+                .for_each(|(kind, entry)| history.add(kind, entry));
+        }
+        history.path = path;
+        history
+    }
+
+    /* @brief records an accepted input as the newest entry, an identical older
+     *        entry is removed rather than kept twice
+     */
+    pub fn add(&mut self, kind: PromptKind, entry: &str) {
+        let entries = self.entries.entry(kind).or_default();
+        entries.retain(|it| it != entry);
+        entries.push(entry.to_string());
+        if entries.len() > HISTORY_LIMIT {
+            entries.drain(..entries.len() - HISTORY_LIMIT);
+        }
+    }
+
+    /* @brief entry at pos counting back from the newest, 0 is the newest
+     */
+    pub fn get(&self, kind: PromptKind, pos: usize) -> Option<&str> {
+        self.entries
+            .get(&kind)
+            .and_then(|entries| entries.iter().rev().nth(pos))
+            .map(|entry| entry.as_str())
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = self.entries.iter().fold(String::new(), |mut accm, (kind, entries)| {
+            entries.iter().for_each(|entry| {
+                accm.push_str(kind.name());
+                accm.push('\t');
+                accm.push_str(entry);
+                accm.push('\n');
+            });
+            accm
+        });
+        fs::write(path, content)
+    }
+}
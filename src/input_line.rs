@@ -0,0 +1,148 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/* @brief single line of editable text used by the prompt
+ *        cursor is a byte index into text and always sits on a char boundary
+ */
+pub struct InputLine {
+    text: String,
+    cursor: usize,
+    // first visible column when the input is wider than the prompt
+    scroll: usize,
+}
+
+impl InputLine {
+    pub fn new() -> Self {
+        Self {
+            text: String::new(),
+            cursor: 0,
+            scroll: 0,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+        self.scroll = 0;
+    }
+
+    // replaces the input, leaving the cursor at the end
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+    }
+
+    pub fn insert_char(&mut self, chr: char) {
+        self.text.insert(self.cursor, chr);
+        self.cursor += chr.len_utf8();
+    }
+
+    // pasted text is flattened onto one line, the prompt has no rows
+    pub fn insert_str(&mut self, str: &str) {
+        str.chars()
+            .filter(|chr| *chr != '\r')
+            .map(|chr| if chr == '\n' || chr == '\t' { ' ' } else { chr })
+            .filter(|chr| !chr.is_control())
+            .for_each(|chr| self.insert_char(chr));
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(prev) = self.prev_boundary() {
+            self.text.drain(prev..self.cursor);
+            self.cursor = prev;
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if let Some(next) = self.next_boundary() {
+            self.text.drain(self.cursor..next);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        if let Some(prev) = self.prev_boundary() {
+            self.cursor = prev;
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if let Some(next) = self.next_boundary() {
+            self.cursor = next;
+        }
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    /* @brief deletes the word before the cursor along with any whitespace
+     *        between the word and the cursor, like CTRL + w in a shell
+     */
+    pub fn delete_word(&mut self) {
+        let before = &self.text[..self.cursor];
+        let trimmed = before.trim_end();
+        let start = trimmed
+            .char_indices()
+            .rev()
+            .find(|(_, chr)| chr.is_whitespace())
+            .map_or(0, |(idx, chr)| idx + chr.len_utf8());
+        self.text.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    // deletes everything before the cursor, like CTRL + u in a shell
+    pub fn delete_to_start(&mut self) {
+        self.text.drain(..self.cursor);
+        self.cursor = 0;
+    }
+
+    /* @brief returns the part of the input that fits in width columns along
+     *        with the column of the cursor inside of it, scrolling horizontally
+     *        so that the cursor is always visible
+     */
+    pub fn view(&mut self, width: usize) -> (String, usize) {
+        let width = width.max(1);
+        let cursor_col = self.text[..self.cursor].width();
+        if cursor_col < self.scroll {
+            self.scroll = cursor_col;
+        }
+        if cursor_col >= self.scroll + width {
+            self.scroll = cursor_col - width + 1;
+        }
+        let mut col = 0;
+        let mut visible = String::new();
+        for chr in self.text.chars() {
+            let chr_width = chr.width().unwrap_or(0);
+            if col >= self.scroll && col + chr_width <= self.scroll + width {
+                visible.push(chr);
+            }
+            col += chr_width;
+        }
+        (visible, cursor_col - self.scroll)
+    }
+
+    fn prev_boundary(&self) -> Option<usize> {
+        self.text[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map(|(idx, _)| idx)
+    }
+
+    fn next_boundary(&self) -> Option<usize> {
+        self.text[self.cursor..]
+            .chars()
+            .next()
+            .map(|chr| self.cursor + chr.len_utf8())
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+use crate::config::config_dir;
+
+/* @brief recorded key sequences by register, persisted as one
+ *        `register<TAB>events` line per macro with the events separated
+ *        by spaces, e.g. `a	C-f f o o Enter Home`
+ */
+pub struct MacroRegisters {
+    macros: HashMap<char, Vec<Event>>,
+    path: Option<PathBuf>,
+}
+
+impl MacroRegisters {
+    pub fn load() -> Self {
+        let path = config_dir().map(|dir| dir.join("macros"));
+        let mut macros = HashMap::new();
+        if let Some(content) = path.as_ref().and_then(|path| fs::read_to_string(path).ok()) {
+            for (register, events) in content.lines().filter_map(|line| line.split_once('\t')) {
+                let mut chars = register.chars();
+                let (Some(register), None) = (chars.next(), chars.next()) else {
+                    continue;
+                };
+                // a macro with an event that doesn't parse is dropped whole
+                let events: Option<Vec<Event>> = events.split(' ').map(decode).collect();
+                if let Some(events) = events {
+                    macros.insert(register, events);
+                }
+            }
+        }
+        Self { macros, path }
+    }
+
+    pub fn get(&self, register: char) -> Option<&Vec<Event>> {
+        self.macros.get(&register)
+    }
+
+    pub fn set(&mut self, register: char, events: Vec<Event>) {
+        self.macros.insert(register, events);
+    }
+
+    // writes every register, returns how many macros were saved
+    pub fn save(&self) -> io::Result<usize> {
+        let Some(path) = &self.path else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no config directory"));
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut registers: Vec<&char> = self.macros.keys().collect();
+        registers.sort();
+        let content = registers.iter().fold(String::new(), |mut accm, register| {
+            let events: Vec<String> = self.macros[register].iter().filter_map(encode).collect();
+            accm.push_str(&format!("{}\t{}\n", register, events.join(" ")));
+            accm
+        });
+        fs::write(path, content)?;
+        Ok(registers.len())
+    }
+}
+
+const KEY_NAMES: &[(KeyCode, &str)] = &[
+    (KeyCode::Char(' '), "Space"),
+    (KeyCode::Enter, "Enter"),
+    (KeyCode::Esc, "Esc"),
+    (KeyCode::Backspace, "Backspace"),
+    (KeyCode::Delete, "Delete"),
+    (KeyCode::Insert, "Insert"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::BackTab, "BackTab"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Left, "Left"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::Home, "Home"),
+    (KeyCode::End, "End"),
+    (KeyCode::PageUp, "PageUp"),
+    (KeyCode::PageDown, "PageDown"),
+];
+
+const MODIFIER_NAMES: &[(KeyModifiers, &str)] = &[
+    (KeyModifiers::CONTROL, "C-"),
+    (KeyModifiers::ALT, "A-"),
+    (KeyModifiers::SHIFT, "S-"),
+];
+
+/* @brief writes an event as a token without spaces, modifiers as C- A- S-
+ *        prefixes before the key and pastes as Paste: with escaped text
+ */
+fn encode(event: &Event) -> Option<String> {
+    match event {
+        Event::Key(key) => {
+            let mut token: String = MODIFIER_NAMES
+                .iter()
+                .filter(|(modifier, _)| key.modifiers.contains(*modifier))
+                .map(|(_, name)| *name)
+                .collect();
+            match (KEY_NAMES.iter().find(|(code, _)| *code == key.code), key.code) {
+                (Some((_, name)), _) => token.push_str(name),
+                (None, KeyCode::Char(chr)) => token.push(chr),
+                (None, KeyCode::F(num)) => token.push_str(&format!("F{}", num)),
+                _ => return None,
+            }
+            Some(token)
+        }
+        Event::Paste(text) => Some(text.chars().fold(String::from("Paste:"), |mut accm, chr| {
+            match chr {
+                '\\' => accm.push_str("\\\\"),
+                ' ' => accm.push_str("\\s"),
+                '\t' => accm.push_str("\\t"),
+                '\n' => accm.push_str("\\n"),
+                '\r' => accm.push_str("\\r"),
+                chr => accm.push(chr),
+            }
+            accm
+        })),
+        _ => None,
+    }
+}
+
+fn decode(token: &str) -> Option<Event> {
+    if let Some(text) = token.strip_prefix("Paste:") {
+        let mut pasted = String::new();
+        let mut chars = text.chars();
+        while let Some(chr) = chars.next() {
+            pasted.push(match chr {
+                '\\' => match chars.next()? {
+                    's' => ' ',
+                    't' => '\t',
+                    'n' => '\n',
+                    'r' => '\r',
+                    chr => chr,
+                },
+                chr => chr,
+            });
+        }
+        return Some(Event::Paste(pasted));
+    }
+    let mut modifiers = KeyModifiers::NONE;
+    let mut key = token;
+    // a lone character is a key even when it looks like the start of a prefix
+    while key.chars().count() > 1 {
+        match MODIFIER_NAMES.iter().find(|(_, name)| key.starts_with(name)) {
+            Some((modifier, name)) => {
+                modifiers |= *modifier;
+                key = &key[name.len()..];
+            }
+            None => break,
+        }
+    }
+    let mut chars = key.chars();
+    let code = match (chars.next()?, chars.next()) {
+        (chr, None) => KeyCode::Char(chr),
+        _ => match KEY_NAMES.iter().find(|(_, name)| *name == key) {
+            Some((code, _)) => *code,
+            None => KeyCode::F(key.strip_prefix('F')?.parse().ok()?),
+        },
+    };
+    Some(Event::Key(KeyEvent::new(code, modifiers)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Event {
+        Event::Key(KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn encodes_keys_as_tokens() {
+        assert_eq!(encode(&key(KeyCode::Char('f'), KeyModifiers::CONTROL)).as_deref(), Some("C-f"));
+        assert_eq!(encode(&key(KeyCode::Char(' '), KeyModifiers::NONE)).as_deref(), Some("Space"));
+        assert_eq!(encode(&key(KeyCode::Up, KeyModifiers::ALT | KeyModifiers::SHIFT)).as_deref(), Some("A-S-Up"));
+        assert_eq!(encode(&key(KeyCode::F(5), KeyModifiers::NONE)).as_deref(), Some("F5"));
+        assert_eq!(encode(&Event::Paste("a b\\\n".into())).as_deref(), Some("Paste:a\\sb\\\\\\n"));
+        assert_eq!(encode(&Event::FocusGained), None);
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let events = [
+            key(KeyCode::Char('x'), KeyModifiers::NONE),
+            key(KeyCode::Char('C'), KeyModifiers::SHIFT),
+            key(KeyCode::Char('-'), KeyModifiers::CONTROL),
+            key(KeyCode::Enter, KeyModifiers::NONE),
+            key(KeyCode::PageDown, KeyModifiers::CONTROL | KeyModifiers::ALT),
+            key(KeyCode::F(12), KeyModifiers::SHIFT),
+            Event::Paste("fn main() {\n\tx\r\n}\\s".into()),
+        ];
+        for event in events {
+            let token = encode(&event).unwrap();
+            assert!(!token.contains(' '), "{}", token);
+            assert_eq!(decode(&token), Some(event), "{}", token);
+        }
+    }
+
+    #[test]
+    fn single_characters_are_keys_not_prefixes() {
+        assert_eq!(decode("C"), Some(key(KeyCode::Char('C'), KeyModifiers::NONE)));
+        assert_eq!(decode("-"), Some(key(KeyCode::Char('-'), KeyModifiers::NONE)));
+        assert_eq!(decode("C--"), Some(key(KeyCode::Char('-'), KeyModifiers::CONTROL)));
+    }
+
+    #[test]
+    fn rejects_unknown_tokens() {
+        assert_eq!(decode("Nope"), None);
+        assert_eq!(decode("C-"), None);
+        assert_eq!(decode("Fx"), None);
+    }
+}
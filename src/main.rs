@@ -7,6 +7,24 @@ mod editor;
 mod rows;
 mod status;
 mod search;
+mod input_line;
+mod config;
+mod history;
+mod completion;
+mod filetype;
+mod file_format;
+mod editorconfig;
+mod brackets;
+mod words;
+mod undo;
+mod macros;
+mod block;
+mod clipboard;
+mod registers;
+mod substitute;
+mod grep;
+mod buffers;
+mod fuzzy;
 
 use editor::{Editor, CleanUp};
 use std::io::stdout;
@@ -1,28 +1,89 @@
+use crate::block::{self, Block};
+use crate::brackets;
+use crate::buffers::{self, Background, List};
+use crate::clipboard::{Clip, Clipboard};
 use crate::buffer::Buf;
+use crate::completion::PathCompleter;
 use crate::cursor_controller::CursorController;
+use crate::fuzzy::{Finder, FINDER_HEIGHT};
 use crate::global_vars::VERSION;
+use crate::history::{PromptHistory, PromptKind};
+use crate::config::Config;
+use crate::grep::{self, Change, Hit, Matcher};
 use crate::rows::EditorRows;
 use crate::status::StatusMessage;
+use crate::substitute::Substitute;
 use std::cmp::min;
-use std::io::{stdout, Write};
+use std::collections::VecDeque;
+use std::{env, mem};
+use std::path::Path;
+use std::ops::Range;
+use std::io::Write;
 use crossterm::style;
 use crossterm::{
     cursor,
-    event::{self, KeyCode, KeyModifiers},
-    execute, queue, terminal,
+    event::{self, Event, KeyCode, KeyModifiers},
+    queue, terminal,
 };
 use terminal::ClearType;
+use crate::input_line::InputLine;
 use crate::reader::Reader;
-use crate::search::{ SearchIndex, SearchDirection };
+use crate::registers::{self, Registers, SEARCH_REGISTER};
+use crate::search::{self, SearchIndex, SearchDirection};
+use crate::undo::{EditKind, UndoHistory};
+use crate::words;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// how a single cell of a row is drawn
+#[derive(Clone, Copy, Default, PartialEq)]
+struct CellStyle {
+    reverse: bool,
+    underline: bool,
+    foreground: Option<style::Color>,
+    background: Option<style::Color>,
+}
 
 pub struct Output {
     size: (usize, usize),
     buffer: Buf,
     cursor_controller: CursorController,
     pub editor_rows: EditorRows,
+    pub config: Config,
     pub status_message: StatusMessage,
     pub dirty: u64,
+    pub prompt_history: PromptHistory,
     search_index: SearchIndex,
+    // column of the cursor on the status bar while a prompt is open
+    prompt_cursor: Option<usize>,
+    // shown after the prompt text, e.g. that a search wrapped
+    prompt_note: String,
+    // draw tabs, spaces and line ends visibly
+    show_whitespace: bool,
+    undo: UndoHistory,
+    // key presses and pastes, replayed ones first while a macro plays
+    pub reader: Reader,
+    // register a macro is being recorded into
+    pub recording: Option<char>,
+    // whether the last search found nothing or wrapped, stops macro playback
+    pub search_failed: bool,
+    // cursors besides the main one, edits happen at all of them
+    extra_cursors: Vec<(usize, usize)>,
+    // rectangular selection, edits happen on each of its rows
+    block: Option<Block>,
+    // text copied or cut, pasted back with CTRL + v
+    clipboard: Clipboard,
+    // the kill ring and named registers
+    registers: Registers,
+    // what the buffer on screen lists when it is a list buffer
+    list: Option<List>,
+    // the other open buffers, the next one first
+    buffers: VecDeque<Background>,
+    // project searches take the pattern as a regex instead of literally
+    pub grep_regex: bool,
+    // the fuzzy file finder while its prompt is open, drawn over the last rows
+    finder: Option<Finder>,
+    // the unmatched brackets together with the version of the rows they were found in
+    unmatched_brackets: Option<(u64, Vec<(usize, usize)>)>,
 }
 
 impl Output {
@@ -30,64 +91,92 @@ impl Output {
         let size = terminal::size()
             .map(|(x, y)| (x as usize, (y - 1) as usize))
             .unwrap();
+        let config = Config::load();
+        let editor_rows = EditorRows::new(&config);
+        let clipboard = Clipboard::new(&config, editor_rows.filetype().name);
+        let show_whitespace = config
+            .get(editor_rows.filetype().name, "show_whitespace")
+            .unwrap_or(false);
         Self {
             size,
             buffer: Buf::new(),
             cursor_controller: CursorController::new(size),
-            editor_rows: EditorRows::new(),
+            editor_rows,
+            config,
             status_message: StatusMessage::new("HELP: CTRL + {q: exits, s: save, f: search}"),
             dirty: 0,
+            prompt_history: PromptHistory::load(),
             search_index: SearchIndex::new(),
+            prompt_cursor: None,
+            prompt_note: String::new(),
+            show_whitespace,
+            undo: UndoHistory::new(),
+            reader: Reader::new(),
+            recording: None,
+            search_failed: false,
+            extra_cursors: Vec::new(),
+            block: None,
+            clipboard,
+            registers: Registers::load(),
+            list: None,
+            buffers: VecDeque::new(),
+            grep_regex: false,
+            finder: None,
+            unmatched_brackets: None,
         }
     }
 
-    pub fn clear_screen(&self) -> crossterm::Result<()> {
-        execute!(stdout(), terminal::Clear(ClearType::All))?;
-        execute!(stdout(), cursor::MoveTo(0, 0))
-    }
-
     pub fn draw_rows(&mut self) {
         // the terminal size [)
         let display_x = self.size.0;
         let display_y = self.size.1;
         // the position in the buffer of the cursor [)
-        let buffer_x = self.cursor_controller.col_offset;
         let buffer_y = self.cursor_controller.row_offset;
         let buffer_length = self.editor_rows.num_rows();
         let line_marker = "🔥";
+        let mut marks = self.bracket_marks();
+        // the extra cursors are drawn as reversed cells
+        marks.extend(self.extra_cursors.iter().map(|cursor| {
+            let style = CellStyle {
+                reverse: true,
+                ..CellStyle::default()
+            };
+            (*cursor, style)
+        }));
+        let overlay = self
+            .finder
+            .as_ref()
+            .map(|finder| finder.lines(min(FINDER_HEIGHT, display_y)))
+            .unwrap_or_default();
+        let overlay_y = display_y - overlay.len();
         for i in 0..display_y {
             let rend_y = i + buffer_y;
-            if i >= buffer_length {
+            if i >= overlay_y {
+                let (line, selected) = &overlay[i - overlay_y];
+                self.draw_overlay_line(line, *selected);
+            } else if i >= buffer_length {
                 self.buffer.push_str(line_marker);
                 if i == display_y / 20 && buffer_length == 0 {
                     let mut welcome = format!("🔥 Editor --- Version {}", VERSION);
-                    if welcome.len() > display_x as usize {
-                        welcome.truncate(display_x as usize);
+                    if welcome.len() > display_x {
+                        welcome.truncate(display_x);
                     }
-                    let padding = (display_x as usize - welcome.len()) / 2;
+                    let padding = (display_x - welcome.len()) / 2;
                     (2..padding).for_each(|_| self.buffer.push_chr(' '));
                     self.buffer.push_str(&welcome);
                 }
                 if i == display_y / 20 + 2 && buffer_length == 0 {
-                    let mut welcome = format!("CTRL + q to quit");
-                    if welcome.len() > display_x as usize {
-                        welcome.truncate(display_x as usize);
+                    let mut welcome = String::from("CTRL + q to quit");
+                    if welcome.len() > display_x {
+                        welcome.truncate(display_x);
                     }
-                    let padding = (display_x as usize - welcome.len()) / 2;
+                    let padding = (display_x - welcome.len()) / 2;
                     (0..padding).for_each(|_| self.buffer.push_chr(' '));
                     self.buffer.push_str(&welcome);
                 }
-            } else {
-                // determine row_length and the row to be rendered
-                let (row_len, render_row) = if rend_y >= buffer_length {
-                    (0, 0)
-                } else {
-                    (self.editor_rows.get_render(rend_y).len(), rend_y)
-                };
-                let len = min(row_len.saturating_sub(buffer_x), display_x);
-                let start = if len == 0 { 0 } else { buffer_x };
-                self.buffer
-                    .push_str(&self.editor_rows.get_render(render_row)[start..start + len])
+            } else if rend_y < buffer_length {
+                let row_marks: Vec<_> = marks.iter().filter(|(pos, _)| pos.1 == rend_y).copied().collect();
+                self.draw_row(rend_y, &row_marks);
             }
             queue!(self.buffer, terminal::Clear(ClearType::UntilNewLine)).unwrap();
             if i < display_y {
@@ -97,6 +186,216 @@ impl Output {
         // self.buffer.push_str("test");
     }
 
+    // a row of the finder's list, the selected one in reverse video
+    fn draw_overlay_line(&mut self, line: &str, selected: bool) {
+        let mut width = 2;
+        let line: String = line
+            .chars()
+            .take_while(|chr| {
+                width += chr.width().unwrap_or(0);
+                width <= self.size.0
+            })
+            .collect();
+        if selected {
+            self.buffer.push_str(&style::Attribute::Reverse.to_string());
+            self.buffer.push_str("> ");
+            self.buffer.push_str(&line);
+            (min(width, self.size.0)..self.size.0).for_each(|_| self.buffer.push_chr(' '));
+            self.buffer.push_str(&style::Attribute::Reset.to_string());
+        } else {
+            self.buffer.push_str("  ");
+            self.buffer.push_str(&line);
+        }
+    }
+
+    /* @brief draws the columns of row at that fit on screen, expanding tabs,
+     *        showing the selection in reverse video and marking trailing
+     *        whitespace and indentation mixing tabs and spaces, with
+     *        show_whitespace tabs, spaces and the line end are drawn visibly,
+     *        marks color single cells of the row like brackets and extra
+     *        cursors
+     */
+    fn draw_row(&mut self, at: usize, marks: &[((usize, usize), CellStyle)]) {
+        let start = self.cursor_controller.col_offset;
+        let end = start + self.size.0;
+        let tab_stop = self.editor_rows.indent().tab_width;
+        let show_whitespace = self.show_whitespace;
+        let selection = self.cursor_controller.selection();
+        // a block selects by render column, an empty one is underlined
+        let block = self
+            .block
+            .filter(|block| block.rows().contains(&at))
+            .map(|block| block.columns());
+        let is_selected = |x: usize, col: usize| match &block {
+            Some(columns) => columns.contains(&col),
+            None => selection.is_some_and(|((start_x, start_y), (end_x, end_y))| {
+                (at, x) >= (start_y, start_x) && (at, x) < (end_y, end_x)
+            }),
+        };
+        let is_block_column = |col: usize| block.as_ref().is_some_and(|columns| columns.is_empty() && columns.start == col);
+        let row = self.editor_rows.get_row(at).row_content.clone();
+        let trailing_start = row.trim_end_matches([' ', '\t']).len();
+        let indent_end = row.len() - row.trim_start_matches([' ', '\t']).len();
+        let mixed_indent = row[..indent_end].contains(' ') && row[..indent_end].contains('\t');
+        let mut col = 0;
+        let mut current = CellStyle::default();
+        for (idx, chr) in row.char_indices() {
+            let width = if chr == '\t' {
+                tab_stop - col % tab_stop
+            } else {
+                chr.width().unwrap_or(0)
+            };
+            if col + width > start && col < end {
+                let mut cell = CellStyle {
+                    reverse: is_selected(idx, col),
+                    underline: is_block_column(col),
+                    ..CellStyle::default()
+                };
+                if idx >= trailing_start {
+                    cell.background = Some(style::Color::DarkRed);
+                } else if mixed_indent && idx < indent_end {
+                    cell.background = Some(style::Color::DarkYellow);
+                }
+                if show_whitespace && (chr == '\t' || chr == ' ') {
+                    cell.foreground = Some(style::Color::DarkGrey);
+                }
+                for (_, mark) in marks.iter().filter(|(pos, _)| pos.0 == idx) {
+                    cell.reverse |= mark.reverse;
+                    cell.foreground = mark.foreground.or(cell.foreground);
+                    cell.background = mark.background.or(cell.background);
+                }
+                self.set_style(&mut current, cell);
+                if chr == '\t' || col < start || col + width > end {
+                    // tabs and wide characters cut off by the screen edge are blanks
+                    for cell_col in col.max(start)..min(col + width, end) {
+                        self.buffer.push_chr(match (show_whitespace, chr) {
+                            (true, '\t') if cell_col == col => '→',
+                            (true, '\t') => '·',
+                            _ => ' ',
+                        });
+                    }
+                } else if show_whitespace && chr == ' ' {
+                    self.buffer.push_chr('·');
+                } else if !chr.is_control() {
+                    self.buffer.push_chr(chr);
+                }
+            }
+            col += width;
+            if col >= end {
+                break;
+            }
+        }
+        // a selection continuing onto the next row or a cursor covers the line end
+        let eol_selected = is_selected(row.len(), col)
+            || marks.iter().any(|(pos, mark)| pos.0 == row.len() && mark.reverse);
+        let eol_underline = is_block_column(col);
+        if col >= start && col < end && (show_whitespace || eol_selected || eol_underline) {
+            let cell = CellStyle {
+                reverse: eol_selected,
+                underline: eol_underline,
+                foreground: show_whitespace.then_some(style::Color::DarkGrey),
+                background: None,
+            };
+            self.set_style(&mut current, cell);
+            self.buffer.push_chr(if show_whitespace { '¬' } else { ' ' });
+        }
+        self.set_style(&mut current, CellStyle::default());
+    }
+
+    /* @brief the bracket under the cursor, or just before it when there is
+     *        none under it, e.g. after typing a closer
+     */
+    fn cursor_bracket(&self) -> Option<(usize, usize)> {
+        let (x, y) = (self.cursor_controller.cursor_x, self.cursor_controller.cursor_y);
+        if brackets::bracket_at(&self.editor_rows, x, y).is_some() {
+            return Some((x, y));
+        }
+        if y >= self.editor_rows.num_rows() {
+            return None;
+        }
+        let before = self.editor_rows.get_row(y).row_content.get(..x)?;
+        let (prev, _) = before.char_indices().next_back()?;
+        brackets::bracket_at(&self.editor_rows, prev, y).map(|_| (prev, y))
+    }
+
+    /* @brief how the brackets on screen are colored, unmatched ones in red
+     *        and the bracket at the cursor together with its match
+     *        highlighted, the unmatched ones are only looked for again after
+     *        an edit
+     */
+    fn bracket_marks(&mut self) -> Vec<((usize, usize), CellStyle)> {
+        let unmatched = CellStyle {
+            foreground: Some(style::Color::Red),
+            ..CellStyle::default()
+        };
+        let matched = CellStyle {
+            background: Some(style::Color::DarkCyan),
+            ..CellStyle::default()
+        };
+        let version = self.editor_rows.version();
+        if self.unmatched_brackets.as_ref().is_none_or(|(cached, _)| *cached != version) {
+            self.unmatched_brackets = Some((version, brackets::unmatched(&self.editor_rows)));
+        }
+        let offset = self.cursor_controller.row_offset;
+        let visible = offset..offset + self.size.1;
+        let mut marks: Vec<((usize, usize), CellStyle)> = self
+            .unmatched_brackets
+            .iter()
+            .flat_map(|(_, positions)| positions)
+            .filter(|pos| visible.contains(&pos.1))
+            .map(|pos| (*pos, unmatched))
+            .collect();
+        if let Some(pos) = self.cursor_bracket() {
+            if let Some(partner) = brackets::find_match(&self.editor_rows, pos.0, pos.1) {
+                marks.insert(0, (pos, matched));
+                marks.insert(0, (partner, matched));
+            }
+        }
+        marks
+    }
+
+    // moves the cursor to the bracket matching the one at the cursor
+    pub fn jump_to_bracket(&mut self) {
+        self.single_cursor();
+        let partner = self
+            .cursor_bracket()
+            .and_then(|(x, y)| brackets::find_match(&self.editor_rows, x, y));
+        match partner {
+            Some((x, y)) => {
+                self.undo.seal();
+                self.cursor_controller.anchor = None;
+                self.cursor_controller.cursor_x = x;
+                self.cursor_controller.cursor_y = y;
+            }
+            None => self.status_message.set_message("No matching bracket".into()),
+        }
+    }
+
+    // switches the terminal to style when it differs from current
+    fn set_style(&mut self, current: &mut CellStyle, style: CellStyle) {
+        if *current == style {
+            return;
+        }
+        queue!(self.buffer, style::SetAttribute(style::Attribute::Reset)).unwrap();
+        if style.reverse {
+            queue!(self.buffer, style::SetAttribute(style::Attribute::Reverse)).unwrap();
+        }
+        if style.underline {
+            queue!(self.buffer, style::SetAttribute(style::Attribute::Underlined)).unwrap();
+        }
+        if let Some(color) = style.foreground {
+            queue!(self.buffer, style::SetForegroundColor(color)).unwrap();
+        }
+        if let Some(color) = style.background {
+            queue!(self.buffer, style::SetBackgroundColor(color)).unwrap();
+        }
+        *current = style;
+    }
+
+    pub fn toggle_whitespace(&mut self) {
+        self.show_whitespace = !self.show_whitespace;
+    }
+
     pub fn draw_status_bar(&mut self) {
         self.buffer.push_str(&style::Attribute::Reverse.to_string());
 
@@ -104,19 +403,39 @@ impl Output {
             Some(msg) => msg.into(),
             None => {
                 format!(
-                    "{} {} -- {} lines",
-                    self.editor_rows
+                    "{} {} -- {} lines -- {} {}{}{}",
+                    self.list.as_ref().map(List::title).unwrap_or_else(|| self
+                        .editor_rows
                         .filename
                         .as_ref()
                         .and_then(|path| path.file_name())
                         .and_then(|name| name.to_str())
-                        .unwrap_or("[No Name]"),
+                        .unwrap_or("[No Name]")),
                     if self.dirty > 0 { "Modified!" } else { "" },
-                    self.editor_rows.num_rows()
+                    self.editor_rows.num_rows(),
+                    self.editor_rows.filetype().name,
+                    self.editor_rows.indent(),
+                    self.recording
+                        .map(|register| format!(" -- recording @{}", register))
+                        .unwrap_or_default(),
+                    if self.extra_cursors.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" -- {} cursors", self.extra_cursors.len() + 1)
+                    },
                 )
             }
         };
-        let info_len = min(info.len(), self.size.0);
+        // truncate by display width, the message may hold prompt input
+        let mut info_len = 0;
+        let info: String = info
+            .chars()
+            .take_while(|chr| {
+                info_len += chr.width().unwrap_or(0);
+                info_len <= self.size.0
+            })
+            .collect();
+        let info_len = info.width();
         let line_info: String = if self.editor_rows.num_rows() == 0 {
             String::from("Empty File")
         } else {
@@ -138,9 +457,13 @@ impl Output {
             )
         };
         let line_info_len = line_info.len();
-        self.buffer.push_str(&info[..info_len]);
-        (info_len..self.size.0 - line_info_len).for_each(|_| self.buffer.push_chr(' '));
-        self.buffer.push_str(&line_info);
+        self.buffer.push_str(&info);
+        if info_len + line_info_len <= self.size.0 {
+            (info_len..self.size.0 - line_info_len).for_each(|_| self.buffer.push_chr(' '));
+            self.buffer.push_str(&line_info);
+        } else {
+            (info_len..self.size.0).for_each(|_| self.buffer.push_chr(' '));
+        }
         self.buffer.push_str(&style::Attribute::Reset.to_string());
     }
 
@@ -151,8 +474,13 @@ impl Output {
         self.draw_status_bar();
         // cursor_{x,y} is the position in the actual text buffer
         //  adjust be offsetting
-        let cursor_x = self.cursor_controller.render_x - self.cursor_controller.col_offset;
-        let cursor_y = self.cursor_controller.cursor_y - self.cursor_controller.row_offset;
+        let (cursor_x, cursor_y) = match self.prompt_cursor {
+            Some(prompt_x) => (prompt_x, self.size.1),
+            None => (
+                self.cursor_controller.render_x - self.cursor_controller.col_offset,
+                self.cursor_controller.cursor_y - self.cursor_controller.row_offset,
+            ),
+        };
 
         queue!(
             self.buffer,
@@ -162,22 +490,283 @@ impl Output {
     }
 
     pub fn move_cursor(&mut self, direction: event::KeyCode) {
+        self.undo.seal();
+        self.block = None;
+        self.cursor_controller.anchor = None;
+        self.move_extra_cursors(|cursor, rows| cursor.move_cursor(direction, rows));
         self.cursor_controller
             .move_cursor(direction, &self.editor_rows)
     }
 
-    pub fn insert_char(&mut self, char: char) {
+    pub fn move_word(&mut self, direction: event::KeyCode) {
+        self.undo.seal();
+        self.block = None;
+        self.cursor_controller.anchor = None;
+        self.move_extra_cursors(|cursor, rows| cursor.move_word(direction, rows));
+        self.cursor_controller
+            .move_word(direction, &self.editor_rows)
+    }
+
+    // moves the cursor by a word extending the selection
+    pub fn select_word(&mut self, direction: event::KeyCode) {
+        self.single_cursor();
+        self.undo.seal();
+        let cursor = &mut self.cursor_controller;
+        if cursor.anchor.is_none() {
+            cursor.anchor = Some((cursor.cursor_x, cursor.cursor_y));
+        }
+        cursor.move_word(direction, &self.editor_rows)
+    }
+
+    /* @brief deletes from the cursor to where moving by a word would go,
+     *        at the start or end of a row the line break is deleted instead
+     */
+    fn delete_word_at_cursor(&mut self, direction: event::KeyCode) {
+        let (cursor_x, cursor_y) = (self.cursor_controller.cursor_x, self.cursor_controller.cursor_y);
+        let mut target = self.cursor_controller.clone();
+        target.move_word(direction, &self.editor_rows);
+        if target.cursor_y != cursor_y {
+            match direction {
+                KeyCode::Left => self.backspace_at_cursor(),
+                _ => self.del_at_cursor(),
+            }
+            return;
+        }
+        if target.cursor_x == cursor_x {
+            return;
+        }
+        let from = min(cursor_x, target.cursor_x);
+        let to = cursor_x.max(target.cursor_x);
+        self.editor_rows.delete_range(cursor_y, from, to);
+        self.cursor_controller.anchor = None;
+        self.cursor_controller.cursor_x = from;
+        self.dirty += 1;
+    }
+
+    /* @brief moves the cursor extending the selection, the selection starts
+     *        where the cursor was if there wasn't one
+     */
+    pub fn select(&mut self, direction: event::KeyCode) {
+        self.extra_cursors.clear();
+        self.undo.seal();
+        if self.block.is_some() {
+            self.block_select(direction);
+            return;
+        }
+        let cursor = &mut self.cursor_controller;
+        if cursor.anchor.is_none() {
+            cursor.anchor = Some((cursor.cursor_x, cursor.cursor_y));
+        }
+        cursor.move_cursor(direction, &self.editor_rows)
+    }
+
+    /* @brief indents the selected rows by one level, without a selection
+     *        one level is inserted at the cursor, as spaces up to the next
+     *        tab stop when tabs are expanded
+     */
+    pub fn tab(&mut self) {
+        self.single_cursor();
+        self.record_edit(EditKind::Other);
+        let indent = self.editor_rows.indent();
+        if let Some(rows) = self.cursor_controller.selected_rows() {
+            let unit = indent.unit();
+            for at in rows {
+                if !self.editor_rows.get_row(at).row_content.is_empty() {
+                    self.editor_rows.insert_str(at, 0, &unit);
+                    self.shift_x(at, 0, unit.len() as isize);
+                }
+            }
+            self.dirty += 1;
+        } else if indent.expand_tabs {
+            let render_x = if self.cursor_controller.cursor_y < self.editor_rows.num_rows() {
+                let row = self.editor_rows.get_row(self.cursor_controller.cursor_y);
+                self.cursor_controller
+                    .get_render_x(row, self.cursor_controller.cursor_x, indent.tab_width)
+            } else {
+                0
+            };
+            let indent_width = indent.indent_width();
+            (0..indent_width - render_x % indent_width).for_each(|_| self.insert_char(' '));
+        } else {
+            self.insert_char('\t');
+        }
+    }
+
+    /* @brief removes one level of indentation from the selected rows or
+     *        the cursor row
+     */
+    pub fn back_tab(&mut self) {
+        self.single_cursor();
+        self.record_edit(EditKind::Other);
+        let cursor_y = self.cursor_controller.cursor_y;
+        let rows = self
+            .cursor_controller
+            .selected_rows()
+            .unwrap_or(cursor_y..cursor_y + 1);
+        let indent_width = self.editor_rows.indent().indent_width();
+        for at in rows.start..min(rows.end, self.editor_rows.num_rows()) {
+            let row = &self.editor_rows.get_row(at).row_content;
+            let level = if row.starts_with('\t') {
+                1
+            } else {
+                min(row.len() - row.trim_start_matches(' ').len(), indent_width)
+            };
+            if level > 0 {
+                self.editor_rows.delete_range(at, 0, level);
+                self.shift_x(at, 0, -(level as isize));
+                self.dirty += 1;
+            }
+        }
+    }
+
+    /* @brief moves the cursor and anchor on row at after delta bytes were
+     *        added or removed at byte from, positions inside removed text
+     *        end up at from
+     */
+    fn shift_x(&mut self, at: usize, from: usize, delta: isize) {
+        let shift = |x: usize| {
+            if x < from {
+                x
+            } else if delta >= 0 {
+                x + delta as usize
+            } else {
+                x.saturating_sub(delta.unsigned_abs()).max(from)
+            }
+        };
+        let cursor = &mut self.cursor_controller;
+        if cursor.cursor_y == at {
+            cursor.cursor_x = shift(cursor.cursor_x);
+        }
+        if let Some((x, y)) = cursor.anchor.as_mut() {
+            if *y == at {
+                *x = shift(*x);
+            }
+        }
+    }
+
+    /* @brief inserts a typed character, an auto pair opener also inserts
+     *        its closer after the cursor and typing the closer right before
+     *        it steps over it, quotes only pair away from word characters
+     */
+    fn insert_char_at_cursor(&mut self, char: char) {
+        let cursor_y = self.cursor_controller.cursor_y;
+        let cursor_x = self.cursor_controller.cursor_x;
+        let (prev, next) = if cursor_y < self.editor_rows.num_rows() {
+            let content = &self.editor_rows.get_row(cursor_y).row_content;
+            (content[..cursor_x].chars().next_back(), content[cursor_x..].chars().next())
+        } else {
+            (None, None)
+        };
+        let pairs = self.editor_rows.auto_pairs();
+        let extra = self.editor_rows.word_chars();
+        let is_word = |chr: Option<char>| chr.is_some_and(|chr| chr.is_alphanumeric() || extra.contains(chr));
+        if next == Some(char) && pairs.iter().any(|(_, closer)| *closer == char) {
+            self.cursor_controller.anchor = None;
+            self.cursor_controller.cursor_x += char.len_utf8();
+            return;
+        }
+        let closer = pairs
+            .iter()
+            .find(|(opener, _)| *opener == char)
+            .map(|(_, closer)| *closer)
+            .filter(|closer| {
+                // pair only in front of whitespace or another closer
+                let free = next.is_none_or(|next| {
+                    next.is_whitespace() || pairs.iter().any(|(_, closer)| *closer == next)
+                });
+                free && !(*closer == char && (is_word(prev) || is_word(next)))
+            });
+        // a closer typed on a blank row takes it back one indentation level
+        if cursor_y < self.editor_rows.num_rows() && self.editor_rows.filetype().is_closer(char) {
+            self.dedent_blank_row();
+        }
+        self.insert_raw_char(char);
+        if let Some(closer) = closer {
+            self.editor_rows
+                .insert_char(self.cursor_controller.cursor_y, self.cursor_controller.cursor_x, closer);
+        }
+    }
+
+    // inserts char at the cursor as is, without auto pairing or dedenting
+    fn insert_raw_char(&mut self, char: char) {
+        self.cursor_controller.anchor = None;
         if self.cursor_controller.cursor_y == self.editor_rows.num_rows() {
             self.editor_rows.insert_row();
         }
         self.editor_rows
-            .get_row_mut(self.cursor_controller.cursor_y)
-            .insert_char(char, self.cursor_controller.cursor_x);
-        self.cursor_controller.cursor_x += 1;
+            .insert_char(self.cursor_controller.cursor_y, self.cursor_controller.cursor_x, char);
+        self.cursor_controller.cursor_x += char.len_utf8();
         self.dirty += 1;
     }
 
-    pub fn backspace(&mut self) {
+    // the auto pair the cursor sits in the middle of with nothing between
+    fn empty_pair_at_cursor(&self) -> Option<(char, char)> {
+        let cursor_y = self.cursor_controller.cursor_y;
+        let cursor_x = self.cursor_controller.cursor_x;
+        if cursor_y >= self.editor_rows.num_rows() {
+            return None;
+        }
+        let content = &self.editor_rows.get_row(cursor_y).row_content;
+        let prev = content[..cursor_x].chars().next_back()?;
+        let next = content[cursor_x..].chars().next()?;
+        self.editor_rows
+            .auto_pairs()
+            .iter()
+            .find(|pair| **pair == (prev, next))
+            .copied()
+    }
+
+    /* @brief removes one level of indentation from the cursor row when it
+     *        holds nothing but whitespace, used before typing a closer
+     */
+    fn dedent_blank_row(&mut self) {
+        let cursor_y = self.cursor_controller.cursor_y;
+        let content = &self.editor_rows.get_row(cursor_y).row_content;
+        if content.is_empty() || !content.trim_start_matches([' ', '\t']).is_empty() {
+            return;
+        }
+        let level = if content.ends_with('\t') {
+            1
+        } else {
+            min(
+                content.len() - content.trim_end_matches(' ').len(),
+                self.editor_rows.indent().indent_width(),
+            )
+        };
+        let len = content.len();
+        self.editor_rows.delete_range(cursor_y, len - level, len);
+        self.cursor_controller.cursor_x = len - level;
+    }
+
+    /* @brief inserts text from a bracketed paste, newlines split rows
+     */
+    fn paste_at_cursor(&mut self, text: &str) {
+        let mut prev = None;
+        for chr in text.chars() {
+            match chr {
+                // terminals may send \r, \n or \r\n for a line break
+                '\n' if prev == Some('\r') => (),
+                '\r' | '\n' => self.split_row(""),
+                chr => self.insert_raw_char(chr),
+            }
+            prev = Some(chr);
+        }
+    }
+
+    fn backspace_at_cursor(&mut self) {
+        self.cursor_controller.anchor = None;
+        // deleting the opener of an empty pair takes the closer with it
+        if let Some((opener, closer)) = self.empty_pair_at_cursor() {
+            let cursor_x = self.cursor_controller.cursor_x;
+            self.editor_rows.delete_range(
+                self.cursor_controller.cursor_y,
+                cursor_x - opener.len_utf8(),
+                cursor_x + closer.len_utf8(),
+            );
+            self.cursor_controller.cursor_x -= opener.len_utf8();
+            self.dirty += 1;
+            return;
+        }
         let cursor_y = self.cursor_controller.cursor_y;
         let cursor_x = self.cursor_controller.cursor_x;
         let total_rows = self.editor_rows.num_rows();
@@ -192,7 +781,9 @@ impl Output {
             // top left cursor with non empty file, do nothing
             (0, 0, _) => (),
             // if at the end of the file, nothing to delete so move cursor left
-            (c_y, _, t_r) if c_y == t_r => self.move_cursor(KeyCode::Left),
+            (c_y, _, t_r) if c_y == t_r => {
+                self.cursor_controller.move_cursor(KeyCode::Left, &self.editor_rows)
+            }
             // if at the beginning of a line, "delete" the new line by
             //  appending row to previous row and then deleting the current row
             (c_y, 0, _) => {
@@ -201,13 +792,24 @@ impl Output {
                 self.editor_rows.delete_row_shift_up(c_y);
                 self.cursor_controller.cursor_y -= 1;
                 self.cursor_controller.cursor_x = prev_row_len;
-                ()
+            }
+            // in indentation made of spaces, delete back to the previous tab stop
+            (c_y, c_x, _)
+                if self.editor_rows.indent().expand_tabs
+                    && self.editor_rows.get_row(c_y).row_content[..c_x].bytes().all(|chr| chr == b' ') =>
+            {
+                let level = (c_x - 1) % self.editor_rows.indent().indent_width() + 1;
+                self.editor_rows.delete_range(c_y, c_x - level, c_x);
+                self.cursor_controller.cursor_x -= level;
             }
             // normal deletion of a character before the x cursor
             (c_y, c_x, _) => {
-                self.editor_rows.get_row_mut(c_y).delete_char(c_x - 1);
-                self.cursor_controller.cursor_x -= 1;
-                ()
+                let (prev, _) = self.editor_rows.get_row(c_y).row_content[..c_x]
+                    .char_indices()
+                    .next_back()
+                    .unwrap_or((0, ' '));
+                self.editor_rows.delete_char(c_y, prev);
+                self.cursor_controller.cursor_x = prev;
             }
         }
         self.dirty += 1;
@@ -215,7 +817,8 @@ impl Output {
 
     /* @brief peforms text deletion when the delete key is pressed
      */
-    pub fn del(&mut self) {
+    fn del_at_cursor(&mut self) {
+        self.cursor_controller.anchor = None;
         let cursor_y = self.cursor_controller.cursor_y;
         let cursor_x = self.cursor_controller.cursor_x;
         let total_rows = self.editor_rows.num_rows();
@@ -237,14 +840,16 @@ impl Output {
             }
             // normal deletion of a character before the x cursor
             (c_y, c_x, _) => {
-                self.editor_rows.get_row_mut(c_y).delete_char(c_x);
-                ()
+                self.editor_rows.delete_char(c_y, c_x);
             }
         }
         self.dirty += 1;
     }
 
-    pub fn enter(&mut self) {
+    /* @brief splits the row at the cursor, the new row starts with indent
+     */
+    fn split_row(&mut self, indent: &str) {
+        self.cursor_controller.anchor = None;
         let cursor_y = self.cursor_controller.cursor_y;
         let cursor_x = self.cursor_controller.cursor_x;
         let total_rows = self.editor_rows.num_rows();
@@ -258,25 +863,80 @@ impl Output {
                 .row_content
                 .split_off(cursor_x);
             self.editor_rows.insert_row_at(cursor_y + 1);
-            self.editor_rows
-                .get_row_mut(cursor_y + 1)
-                .row_content
-                .push_str(&split);
-            EditorRows::render_row(self.editor_rows.get_row_mut(cursor_y));
-            EditorRows::render_row(self.editor_rows.get_row_mut(cursor_y + 1));
+            let new_row = &mut self.editor_rows.get_row_mut(cursor_y + 1).row_content;
+            new_row.push_str(indent);
+            new_row.push_str(&split);
         }
         self.cursor_controller.cursor_y += 1;
-        self.cursor_controller.cursor_x = 0;
+        self.cursor_controller.cursor_x = indent.len();
         self.dirty += 1;
     }
 
+    /* @brief starts a new row at the cursor with the indentation of the
+     *        current row, one level deeper after a line ending in an opener
+     *        for the filetype, when the cursor sits between a bracket pair
+     *        the closer moves to its own row at the original indentation
+     */
+    fn enter_at_cursor(&mut self) {
+        let cursor_y = self.cursor_controller.cursor_y;
+        if cursor_y == self.editor_rows.num_rows() {
+            self.split_row("");
+            return;
+        }
+        let filetype = self.editor_rows.filetype();
+        let row = &mut self.editor_rows.get_row_mut(cursor_y).row_content;
+        let cursor_x = min(self.cursor_controller.cursor_x, row.len());
+        let indent_len = row.len() - row.trim_start_matches([' ', '\t']).len();
+        let base_indent = row[..min(indent_len, cursor_x)].to_string();
+        let opener = row[..cursor_x]
+            .trim_end()
+            .chars()
+            .last()
+            .filter(|chr| filetype.indent_after.contains(chr));
+        // the rest of the row moves down without its leading whitespace
+        let rest_len = row[cursor_x..].trim_start_matches([' ', '\t']).len();
+        row.drain(cursor_x..row.len() - rest_len);
+        let closer_next = opener
+            .and_then(|opener| filetype.closer(opener))
+            .is_some_and(|closer| row[cursor_x..].starts_with(closer));
+
+        self.cursor_controller.cursor_x = cursor_x;
+        let mut indent = base_indent.clone();
+        if opener.is_some() {
+            indent.push_str(&self.editor_rows.indent().unit());
+        }
+        self.split_row(&indent);
+        if closer_next {
+            self.split_row(&base_indent);
+            self.cursor_controller.cursor_y -= 1;
+            self.cursor_controller.cursor_x = indent.len();
+        }
+    }
+
+    /* @brief runs the save time clean up and writes the buffer, the
+     *        cursor is kept inside the rows that remain
+     */
     pub fn save(&mut self) {
+        self.record_edit(EditKind::Other);
+        let cleaned = self.editor_rows.clean_up();
+        if cleaned > 0 {
+            self.single_cursor();
+            self.dirty += 1;
+            self.cursor_controller.anchor = None;
+            self.cursor_controller.cursor_y =
+                min(self.cursor_controller.cursor_y, self.editor_rows.num_rows());
+        }
         let res = self.editor_rows.save();
         match res {
             Err(err) => {
                 self.status_message.clear_custom_message();
                 self.status_message.set_message(format!("Error {:?}", err.to_string()));
             },
+            Ok(w) if cleaned > 0 => {
+                self.dirty = 0;
+                self.status_message
+                    .set_message(format!("{} bytes written to disk, {} lines cleaned", w, cleaned))
+            }
             Ok(w) => {
                 self.dirty = 0;
                 self.status_message
@@ -286,154 +946,1721 @@ impl Output {
         }
     }
     
-    fn find_callback(output: &mut Output, keyword: &str, key_code: Option<KeyCode>) {
-        match key_code {
-            Some(KeyCode::Esc | KeyCode::Enter) => {
-                output.search_index.reset();
-            },
-            Some(_) => {
-                // these two lines reset the position of search when a new character is typed
-                output.search_index.y_direction = None;
-                output.search_index.x_direction = None;
-                match key_code {
-                    Some(KeyCode::Down) => {
-                        output.search_index.y_direction = SearchDirection::Forward.into()
-                    },
-                    Some(KeyCode::Up) => {
-                        output.search_index.y_direction = SearchDirection::Backward.into()
-                    },
-                    Some(KeyCode::Left) => {
-                        output.search_index.x_direction = SearchDirection::Backward.into()
-                    },
-                    Some(KeyCode::Right) => {
-                        output.search_index.x_direction = SearchDirection::Forward.into()
-                    },
-                    _ => {}
-                } 
-                // resets anytime any key other than up/down/left/right is pressed
-                for i in 0..output.editor_rows.num_rows() {
-                    let row_index = match output.search_index.y_direction {
-                        None => {
-                            if output.search_index.x_direction.is_none() {
-                                output.search_index.y_index = i;
-                            }
-                            output.search_index.y_index
-                        },
-                        Some(ref dir) => {
-                            if matches!(dir, SearchDirection::Forward) {
-                                output.search_index.y_index + i + 1
-                            } else {
-                                let res = output.search_index.y_index.saturating_sub(i);
-                                if res == 0 {
-                                    break;
-                                }
-                                res - 1
-                            }
-                        }
-                    };
-                    if row_index >= output.editor_rows.num_rows() { break; }
-                    let row = output.editor_rows.get_row(row_index);
-
-                    // resets when up/down is pressed
-                    let index = match output.search_index.x_direction {
-                        None => row.render.find(&keyword),
-                        Some(ref dir) => {
-                            let index = if matches!(dir, SearchDirection::Forward) {
-                                let start = min(row.render.len(), output.search_index.x_index + 1);
-                                row.render[start..].find(&keyword).and_then(|index| Some(start + index))
-                            } else {
-                                row.render[..output.search_index.x_index].rfind(&keyword)
-                            };
-                            if index.is_none() {
-                                break;
-                            }
-                            index
-                        }
-                    };
+    pub fn insert_char(&mut self, char: char) {
+        if self.block.is_some() {
+            self.record_edit(EditKind::Insert);
+            self.delete_block();
+            self.block_insert(&char.to_string());
+            return;
+        }
+        self.at_all_cursors(EditKind::Insert, |output| output.insert_char_at_cursor(char));
+    }
 
-                    if let Some(index) = index {
-                        output.cursor_controller.cursor_y = row_index;
-                        output.search_index.y_index = row_index;
-                        output.search_index.x_index = index;
-                        output.cursor_controller.cursor_x = output.cursor_controller.get_render_x(row, index);
-                        break;
-                    }
-                }
+    pub fn paste(&mut self, text: &str) {
+        if self.block.is_some() {
+            self.record_edit(EditKind::Other);
+            if text.contains(['\r', '\n']) {
+                self.paste_rectangle(&text.lines().collect::<Vec<_>>());
+            } else {
+                self.delete_block();
+                self.block_insert(text);
             }
-            None => (),
+            return;
         }
+        self.at_all_cursors(EditKind::Other, |output| output.paste_at_cursor(text));
     }
-    
 
-    pub fn find(&mut self) -> std::io::Result<()> {
-        let res = self.cursor_controller.clone();
-        if prompt!(self, "Search: {} (ESC to Cancel, Enter to Confirm)", Output::find_callback).is_none() {
-            self.cursor_controller = res;
+    pub fn backspace(&mut self) {
+        if self.block.is_some() {
+            self.record_edit(EditKind::Delete);
+            self.block_delete_char(KeyCode::Left);
+            return;
+        }
+        self.at_all_cursors(EditKind::Delete, |output| output.backspace_at_cursor());
+    }
+
+    pub fn del(&mut self) {
+        if self.block.is_some() {
+            self.record_edit(EditKind::Delete);
+            self.block_delete_char(KeyCode::Right);
+            return;
+        }
+        self.at_all_cursors(EditKind::Delete, |output| output.del_at_cursor());
+    }
+
+    pub fn enter(&mut self) {
+        self.block = None;
+        self.at_all_cursors(EditKind::Other, |output| output.enter_at_cursor());
+    }
+
+    pub fn delete_word(&mut self, direction: event::KeyCode) {
+        self.block = None;
+        self.at_all_cursors(EditKind::Other, |output| output.delete_word_at_cursor(direction));
+    }
+
+    /* @brief makes edit at every cursor as one undo step, going from the
+     *        last cursor to the first so an edit never moves text before
+     *        the cursors still to come, cursors already done are kept as
+     *        distances from the end of the buffer and of their row which
+     *        edits earlier on don't change
+     */
+    fn at_all_cursors(&mut self, kind: EditKind, edit: impl Fn(&mut Self)) {
+        self.record_edit(kind);
+        if self.extra_cursors.is_empty() {
+            edit(self);
+            return;
+        }
+        let main = self.cursor_position();
+        let mut cursors = self.extra_cursors.clone();
+        cursors.push(main);
+        cursors.sort_by_key(|(x, y)| std::cmp::Reverse((*y, *x)));
+        cursors.dedup();
+        let mut done: Vec<((usize, usize), bool)> = Vec::new();
+        for (x, y) in cursors {
+            let is_main = (x, y) == main;
+            self.cursor_controller.anchor = None;
+            self.cursor_controller.cursor_x = x;
+            self.cursor_controller.cursor_y = y;
+            edit(self);
+            let (x, y) = self.cursor_position();
+            let row_len = self.row_len(y);
+            let from_end = self.editor_rows.num_rows().saturating_sub(y);
+            done.push(((row_len.saturating_sub(x), from_end), is_main));
+        }
+        self.extra_cursors.clear();
+        let mut main_at = None;
+        let num_rows = self.editor_rows.num_rows();
+        for ((from_row_end, from_end), is_main) in done {
+            let y = num_rows.saturating_sub(from_end);
+            self.cursor_controller.cursor_y = y;
+            self.cursor_controller.cursor_x = self.row_len(y).saturating_sub(from_row_end);
+            self.cursor_controller.clamp_x(&self.editor_rows);
+            let position = self.cursor_position();
+            if !is_main {
+                self.extra_cursors.push(position);
+            } else {
+                main_at = Some(position);
+            }
+        }
+        if let Some((x, y)) = main_at {
+            self.cursor_controller.cursor_x = x;
+            self.cursor_controller.cursor_y = y;
+        }
+        self.dedup_cursors();
+    }
+
+    fn row_len(&self, at: usize) -> usize {
+        if at < self.editor_rows.num_rows() {
+            self.editor_rows.get_row(at).row_content.len()
+        } else {
+            0
+        }
+    }
+
+    // applies a cursor movement to every extra cursor
+    fn move_extra_cursors(&mut self, movement: impl Fn(&mut CursorController, &EditorRows)) {
+        let mut cursor = self.cursor_controller.clone();
+        for (x, y) in self.extra_cursors.iter_mut() {
+            (cursor.cursor_x, cursor.cursor_y) = (*x, *y);
+            movement(&mut cursor, &self.editor_rows);
+            (*x, *y) = (cursor.cursor_x, cursor.cursor_y);
+        }
+        self.dedup_cursors();
+    }
+
+    // cursors that ended up in the same place merge
+    fn dedup_cursors(&mut self) {
+        let main = self.cursor_position();
+        self.extra_cursors.sort_by_key(|(x, y)| (*y, *x));
+        self.extra_cursors.dedup();
+        self.extra_cursors.retain(|cursor| *cursor != main);
+    }
+
+    pub fn clear_cursors(&mut self) {
+        self.extra_cursors.clear();
+        self.block = None;
+    }
+
+    // leaves multiple cursors and block selection before an edit at the cursor alone
+    fn single_cursor(&mut self) {
+        self.extra_cursors.clear();
+        self.block = None;
+    }
+
+    /* @brief adds a cursor on the row above the topmost cursor or below the
+     *        bottommost one, the main cursor moves to it
+     */
+    pub fn add_cursor(&mut self, direction: KeyCode) {
+        self.cursor_controller.anchor = None;
+        let main = self.cursor_position();
+        let mut cursors = self.extra_cursors.clone();
+        cursors.push(main);
+        let (x, y) = if direction == KeyCode::Up {
+            *cursors.iter().min_by_key(|(x, y)| (*y, *x)).unwrap()
+        } else {
+            *cursors.iter().max_by_key(|(x, y)| (*y, *x)).unwrap()
         };
-        Ok(())
+        let y = if direction == KeyCode::Up {
+            match y.checked_sub(1) {
+                Some(y) => y,
+                None => return,
+            }
+        } else if y + 1 < self.editor_rows.num_rows() {
+            y + 1
+        } else {
+            return;
+        };
+        self.extra_cursors.push(main);
+        self.cursor_controller.cursor_x = x;
+        self.cursor_controller.cursor_y = y;
+        self.cursor_controller.clamp_x(&self.editor_rows);
+        self.dedup_cursors();
     }
-}
 
-#[macro_export()]
-macro_rules! prompt {
-    ($output:expr, $args:tt) => {
-        prompt!($output, $args, |&_, _, _| {}) // this ignores the callback when called
-                                                          // with 2 arguments
-    };
-    ($output:expr, $args:tt, $callback:expr) => {{
-        let output:&mut Output = $output;
-        // file name length
-        let mut input: String = String::with_capacity(255);
-        loop {
-            output.status_message.set_message(format!($args, input));
-            output.refresh()?;
-            let key_event = Reader.read_keyevent();
-            match key_event {
-                Some(event::KeyEvent {
-                    code: KeyCode::Enter,
-                    modifiers: KeyModifiers::NONE,
-                    ..
-                }) => {
-                    if !input.is_empty() {
-                        output.status_message.set_message(String::new());
-                        $callback(output, &input, Some(KeyCode::Enter));
-                        break;
-                    }
-                },
-                Some(event::KeyEvent {
-                    code: KeyCode::Backspace,
-                    modifiers: KeyModifiers::NONE,
-                    ..
-                }) => {
-                    if input.len() > 0 {
-                        input.pop();
-                    }
-                },
-                Some(event::KeyEvent {
-                    code: code @ KeyCode::Char(..),
-                    modifiers: case @ (KeyModifiers::NONE| KeyModifiers::SHIFT),
-                    ..
-                }) => input.push(
-                    match code {
-                        KeyCode::Char(char) if matches!(case, KeyModifiers::NONE) => char,
-                        KeyCode::Char(char) if matches!(case, KeyModifiers::SHIFT) => char.to_ascii_uppercase(),
-                        _ => unimplemented!(),
-                    }
-                ),
-                Some(event::KeyEvent {
-                    code: KeyCode::Esc,
-                    ..
-                }) => {
-                    input.clear();
-                    output.status_message.clear_custom_message();
-                    $callback(output, &input, Some(KeyCode::Esc));
-                    break;
-                },
-                _ => (),
+    /* @brief adds a cursor at the end of the next occurrence of the
+     *        selected text or the word at the cursor, the main cursor moves
+     *        to it and the search wraps around the end of the buffer
+     */
+    pub fn add_cursor_at_next(&mut self) {
+        let (cursor_x, cursor_y) = self.cursor_position();
+        if cursor_y >= self.editor_rows.num_rows() {
+            return;
+        }
+        let row = &self.editor_rows.get_row(cursor_y).row_content;
+        let (needle_start, needle) = match self.cursor_controller.selection() {
+            Some(((start_x, start_y), (end_x, end_y))) if start_y == end_y => {
+                (start_x, row[start_x..end_x].to_string())
             }
-            $callback(output, &input, key_event.and_then(|event| Some(event.code)));
+            Some(_) => return,
+            None => match words::word_at(row, cursor_x, self.editor_rows.word_chars()) {
+                Some(word) => (word.start, row[word.clone()].to_string()),
+                None => return,
+            },
+        };
+        // the first time the main cursor goes to the end of its own occurrence
+        if self.extra_cursors.is_empty() || self.cursor_controller.anchor.is_some() {
+            self.cursor_controller.anchor = None;
+            self.cursor_controller.cursor_x = needle_start + needle.len();
+        }
+        let (from_x, from_y) = self.cursor_position();
+        let num_rows = self.editor_rows.num_rows();
+        let found = (0..=num_rows).find_map(|step| {
+            let y = (from_y + step) % num_rows;
+            let row = &self.editor_rows.get_row(y).row_content;
+            let start = if step == 0 { from_x } else { 0 };
+            row[start..].find(&needle).map(|x| (start + x + needle.len(), y))
+        });
+        match found {
+            Some(position) if position != self.cursor_position() => {
+                self.extra_cursors.push(self.cursor_position());
+                (self.cursor_controller.cursor_x, self.cursor_controller.cursor_y) = position;
+                self.dedup_cursors();
+            }
+            _ => self.status_message.set_message(format!("No other occurrence of {}", needle)),
+        }
+    }
+
+    /* @brief puts a cursor at the start of every match of the last search,
+     *        matched the way it was, the main cursor goes to the first
+     *        match after it
+     */
+    pub fn add_cursors_at_matches(&mut self) {
+        let Some(keyword) = self.search_index.keyword.clone() else {
+            self.status_message.set_message("Nothing searched yet".into());
+            return;
+        };
+        let mut matches = Vec::new();
+        for y in 0..self.editor_rows.num_rows() {
+            matches.extend(self.row_matches(y, &keyword).into_iter().map(|x| (x, y)));
+        }
+        if matches.is_empty() {
+            self.status_message.set_message(format!("No matches for {}", keyword));
+            return;
+        }
+        let (cursor_x, cursor_y) = self.cursor_position();
+        let main = matches
+            .iter()
+            .position(|(x, y)| (*y, *x) >= (cursor_y, cursor_x))
+            .unwrap_or(0);
+        (self.cursor_controller.cursor_x, self.cursor_controller.cursor_y) = matches.remove(main);
+        self.cursor_controller.anchor = None;
+        self.extra_cursors = matches;
+        self.status_message
+            .set_message(format!("{} cursors at {}", self.extra_cursors.len() + 1, keyword));
+    }
+
+    /* @brief starts a block selection at the cursor or leaves the one in
+     *        progress, shift movement then grows it by rows and columns
+     */
+    pub fn toggle_block(&mut self) {
+        self.extra_cursors.clear();
+        self.undo.seal();
+        self.cursor_controller.anchor = None;
+        if self.block.take().is_some() {
+            self.status_message.set_message("Block selection off".into());
+            return;
+        }
+        let (x, y) = self.cursor_position();
+        self.block = Some(Block::new(self.render_column(x, y), y));
+        self.status_message
+            .set_message("Block selection (SHIFT + arrows: extend, ALT + b: leave)".into());
+    }
+
+    // the render column of byte x on row y
+    fn render_column(&self, x: usize, y: usize) -> usize {
+        if y >= self.editor_rows.num_rows() {
+            return 0;
+        }
+        let row = &self.editor_rows.get_row(y).row_content;
+        block::width(&row[..min(x, row.len())], self.editor_rows.indent().tab_width)
+    }
+
+    // grows the block selection, its columns may go past the end of rows
+    fn block_select(&mut self, direction: KeyCode) {
+        let Some(mut block) = self.block else {
+            return;
+        };
+        let last_row = self.editor_rows.num_rows().saturating_sub(1);
+        let page = self.size.1;
+        let (col, row) = &mut block.cursor;
+        match direction {
+            KeyCode::Left => *col = col.saturating_sub(1),
+            KeyCode::Right => *col += 1,
+            KeyCode::Home => *col = 0,
+            KeyCode::End => *col = self.render_column(usize::MAX, *row),
+            KeyCode::Up => *row = row.saturating_sub(1),
+            KeyCode::Down => *row = min(*row + 1, last_row),
+            KeyCode::PageUp => *row = row.saturating_sub(page),
+            KeyCode::PageDown => *row = min(*row + page, last_row),
+            _ => (),
+        }
+        self.block = Some(block);
+        self.cursor_to_block();
+    }
+
+    // puts the cursor on the moving corner of the block
+    fn cursor_to_block(&mut self) {
+        let Some(Block { cursor: (col, y), .. }) = self.block else {
+            return;
+        };
+        self.cursor_controller.cursor_y = y;
+        self.cursor_controller.cursor_x = if y < self.editor_rows.num_rows() {
+            let row = &self.editor_rows.get_row(y).row_content;
+            block::byte_at_column(row, col, self.editor_rows.indent().tab_width).0
+        } else {
+            0
+        };
+    }
+
+    // the text inside the block on each of its rows
+    fn block_text(&self) -> Vec<String> {
+        let Some(selected) = self.block else {
+            return Vec::new();
+        };
+        let tab_stop = self.editor_rows.indent().tab_width;
+        selected
+            .rows()
+            .map(|at| {
+                if at >= self.editor_rows.num_rows() {
+                    return String::new();
+                }
+                let row = &self.editor_rows.get_row(at).row_content;
+                row[block::byte_range(row, selected.columns(), tab_stop)].to_string()
+            })
+            .collect()
+    }
+
+    // removes the text inside the block, which collapses to its left column
+    fn delete_block(&mut self) {
+        let Some(mut selected) = self.block else {
+            return;
+        };
+        let columns = selected.columns();
+        let tab_stop = self.editor_rows.indent().tab_width;
+        let rows = selected.rows();
+        for at in rows.start..min(rows.end, self.editor_rows.num_rows()) {
+            let range = block::byte_range(&self.editor_rows.get_row(at).row_content, columns.clone(), tab_stop);
+            if !range.is_empty() {
+                self.editor_rows.delete_range(at, range.start, range.end);
+                self.dirty += 1;
+            }
+        }
+        selected.collapse(columns.start);
+        self.block = Some(selected);
+        self.cursor_to_block();
+    }
+
+    /* @brief inserts text at the left column of the block on each of its
+     *        rows, rows too short to reach the column are padded with spaces
+     */
+    fn block_insert(&mut self, text: &str) {
+        let Some(mut selected) = self.block else {
+            return;
+        };
+        let col = selected.columns().start;
+        let tab_stop = self.editor_rows.indent().tab_width;
+        for at in selected.rows() {
+            while at >= self.editor_rows.num_rows() {
+                self.editor_rows.insert_row();
+            }
+            let (x, padding) = block::byte_at_column(&self.editor_rows.get_row(at).row_content, col, tab_stop);
+            self.editor_rows
+                .insert_str(at, x, &format!("{}{}", " ".repeat(padding), text));
+        }
+        selected.collapse(col + text.width());
+        self.block = Some(selected);
+        self.cursor_to_block();
+        self.dirty += 1;
+    }
+
+    /* @brief deletes the text inside the block, or when it is an empty
+     *        column the character left of it for backspace or right of it
+     *        for delete on each of its rows
+     */
+    fn block_delete_char(&mut self, direction: KeyCode) {
+        let Some(mut selected) = self.block else {
+            return;
+        };
+        let columns = selected.columns();
+        if !columns.is_empty() {
+            self.delete_block();
+            return;
+        }
+        let col = match direction {
+            KeyCode::Left => match columns.start.checked_sub(1) {
+                Some(col) => col,
+                None => return,
+            },
+            _ => columns.start,
+        };
+        let tab_stop = self.editor_rows.indent().tab_width;
+        let rows = selected.rows();
+        for at in rows.start..min(rows.end, self.editor_rows.num_rows()) {
+            if let Some(range) = block::char_at_column(&self.editor_rows.get_row(at).row_content, col, tab_stop) {
+                self.editor_rows.delete_range(at, range.start, range.end);
+                self.dirty += 1;
+            }
+        }
+        selected.collapse(col);
+        self.block = Some(selected);
+        self.cursor_to_block();
+    }
+
+    /* @brief pastes lines as a rectangle, each at the same render column
+     *        on successive rows from the cursor or the block, rows are
+     *        padded or added as needed and the cursor ends after the last
+     */
+    fn paste_rectangle(&mut self, lines: &[&str]) {
+        self.extra_cursors.clear();
+        self.cursor_controller.anchor = None;
+        let (col, y) = match self.block {
+            Some(selected) => {
+                self.delete_block();
+                (selected.columns().start, selected.rows().start)
+            }
+            None => {
+                let (x, y) = self.cursor_position();
+                (self.render_column(x, y), y)
+            }
+        };
+        self.block = None;
+        let tab_stop = self.editor_rows.indent().tab_width;
+        for (idx, line) in lines.iter().enumerate() {
+            while y + idx >= self.editor_rows.num_rows() {
+                self.editor_rows.insert_row();
+            }
+            let (x, padding) = block::byte_at_column(&self.editor_rows.get_row(y + idx).row_content, col, tab_stop);
+            self.editor_rows
+                .insert_str(y + idx, x, &format!("{}{}", " ".repeat(padding), line));
+            self.cursor_controller.cursor_y = y + idx;
+            self.cursor_controller.cursor_x = x + padding + line.len();
+        }
+        self.dirty += 1;
+    }
+
+    // the text of the block or the selection
+    fn copied(&self) -> Option<Clip> {
+        if self.block.is_some() {
+            return Some(Clip {
+                text: self.block_text().join("\n"),
+                block: true,
+            });
+        }
+        let ((start_x, start_y), (end_x, end_y)) = self.cursor_controller.selection()?;
+        let mut text = String::new();
+        for at in start_y..min(end_y + 1, self.editor_rows.num_rows()) {
+            let row = &self.editor_rows.get_row(at).row_content;
+            let from = if at == start_y { min(start_x, row.len()) } else { 0 };
+            let to = if at == end_y { min(end_x, row.len()) } else { row.len() };
+            text.push_str(&row[from..to.max(from)]);
+            if at < end_y {
+                text.push('\n');
+            }
+        }
+        Some(Clip { text, block: false })
+    }
+
+    pub fn copy(&mut self) {
+        match self.copied() {
+            Some(clip) => {
+                let lines = clip.text.split('\n').count();
+                let kind = if clip.block { "block rows" } else { "lines" };
+                self.registers.kill(clip.clone(), true);
+                self.save_registers();
+                match self.clipboard.copy(clip, &mut self.buffer) {
+                    Ok(()) => self.status_message.set_message(format!("Copied {} {}", lines, kind)),
+                    Err(err) => self
+                        .status_message
+                        .set_message(format!("Copied inside the editor only, clipboard error {:?}", err.to_string())),
+                }
+            }
+            None => self.status_message.set_message("Nothing selected".into()),
+        }
+    }
+
+    pub fn cut(&mut self) {
+        let Some(clip) = self.copied() else {
+            self.status_message.set_message("Nothing selected".into());
+            return;
+        };
+        self.record_edit(EditKind::Other);
+        let is_block = clip.block;
+        self.registers.kill(clip.clone(), false);
+        self.save_registers();
+        if let Err(err) = self.clipboard.copy(clip, &mut self.buffer) {
+            self.status_message
+                .set_message(format!("Cut inside the editor only, clipboard error {:?}", err.to_string()));
+        }
+        if is_block {
+            self.delete_block();
+        } else {
+            self.delete_selection();
+        }
+    }
+
+    // pastes what was copied last, a block copy as a rectangle
+    pub fn paste_clipboard(&mut self) {
+        match self.clipboard.paste() {
+            Some(clip) => self.paste_clip(clip),
+            None => self.status_message.set_message("Nothing copied".into()),
+        }
+    }
+
+    fn paste_clip(&mut self, clip: Clip) {
+        if clip.block {
+            self.record_edit(EditKind::Other);
+            self.paste_rectangle(&clip.text.split('\n').collect::<Vec<_>>());
+        } else {
+            self.paste(&clip.text);
+        }
+    }
+
+    fn save_registers(&mut self) {
+        if let Err(err) = self.registers.save() {
+            self.status_message
+                .set_message(format!("Error saving registers {:?}", err.to_string()));
+        }
+    }
+
+    // copies the block or the selection into the register named by the next key
+    pub fn copy_to_register(&mut self) -> crossterm::Result<()> {
+        let Some(clip) = self.copied() else {
+            self.status_message.set_message("Nothing selected".into());
+            return Ok(());
+        };
+        self.status_message
+            .set_message("Copy into register: press a-z".into());
+        self.refresh()?;
+        let key = loop {
+            if let Some(event) = self.reader.read_event() {
+                break event;
+            }
+        };
+        match key {
+            Event::Key(event::KeyEvent {
+                code: KeyCode::Char(register @ 'a'..='z'),
+                modifiers: KeyModifiers::NONE,
+                ..
+            }) => {
+                self.registers.set(register, clip);
+                self.status_message
+                    .set_message(format!("Copied into @{}", register));
+                self.save_registers();
+            }
+            _ => self.status_message.set_message("Copy cancelled".into()),
+        }
+        Ok(())
+    }
+
+    /* @brief browses the registers and the kill ring on the status bar,
+     *        Up/Down step through the entries, Enter pastes the one shown
+     *        and typing a register name pastes that register
+     */
+    pub fn pick_register(&mut self) -> crossterm::Result<()> {
+        let entries = self.registers.entries();
+        if entries.is_empty() {
+            self.status_message.set_message("Nothing copied yet".into());
+            return Ok(());
+        }
+        let mut idx = 0;
+        let chosen = loop {
+            let (label, clip) = &entries[idx];
+            self.status_message.set_message(format!(
+                "Paste [{}/{}] {} (Up/Down: browse, Enter: paste, a-z 0 /: register, ESC: cancel): {}",
+                idx + 1,
+                entries.len(),
+                label,
+                clip.text.replace('\n', "⏎").replace('\t', " "),
+            ));
+            self.refresh()?;
+            let Some(Event::Key(key)) = self.reader.read_event() else {
+                continue;
+            };
+            match key.code {
+                KeyCode::Up | KeyCode::Left => idx = idx.checked_sub(1).unwrap_or(entries.len() - 1),
+                KeyCode::Down | KeyCode::Right => idx = (idx + 1) % entries.len(),
+                KeyCode::Enter => break Some(clip.clone()),
+                KeyCode::Char(register)
+                    if registers::is_register(register)
+                        && !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+                {
+                    match self.registers.get(register) {
+                        Some(clip) => break Some(clip.clone()),
+                        None => {
+                            self.status_message.set_message(format!("@{} is empty", register));
+                            return Ok(());
+                        }
+                    }
+                }
+                KeyCode::Esc => break None,
+                _ => (),
+            }
+        };
+        self.status_message.clear_custom_message();
+        if let Some(clip) = chosen {
+            self.paste_clip(clip);
+        }
+        Ok(())
+    }
+
+    // removes the selected text, the cursor goes to where it started
+    fn delete_selection(&mut self) {
+        let Some(((start_x, start_y), (end_x, end_y))) = self.cursor_controller.selection() else {
+            return;
+        };
+        self.cursor_controller.anchor = None;
+        let num_rows = self.editor_rows.num_rows();
+        if start_y >= num_rows {
+            return;
+        }
+        let last = min(end_y, num_rows - 1);
+        let last_len = self.editor_rows.get_row(last).row_content.len();
+        let end_x = if end_y == last { min(end_x, last_len) } else { last_len };
+        let tail = self.editor_rows.get_row(last).row_content[end_x..].to_string();
+        (start_y + 1..=last).rev().for_each(|at| self.editor_rows.delete_row(at));
+        let row = &mut self.editor_rows.get_row_mut(start_y).row_content;
+        row.truncate(start_x);
+        row.push_str(&tail);
+        self.cursor_controller.cursor_x = start_x;
+        self.cursor_controller.cursor_y = start_y;
+        self.dirty += 1;
+    }
+
+    pub fn cursor_position(&self) -> (usize, usize) {
+        (self.cursor_controller.cursor_x, self.cursor_controller.cursor_y)
+    }
+
+    // remembers the buffer before an edit so that it can be undone
+    fn record_edit(&mut self, kind: EditKind) {
+        let cursor = self.cursor_position();
+        self.undo.record(kind, &self.editor_rows, cursor);
+    }
+
+    // puts the cursor where an undo or redo left it
+    fn restore(&mut self, cursor: (usize, usize)) {
+        self.single_cursor();
+        self.cursor_controller.anchor = None;
+        (self.cursor_controller.cursor_x, self.cursor_controller.cursor_y) = cursor;
+        self.cursor_controller.cursor_y =
+            min(self.cursor_controller.cursor_y, self.editor_rows.num_rows());
+        self.cursor_controller.clamp_x(&self.editor_rows);
+        self.dirty += 1;
+    }
+
+    pub fn undo(&mut self) {
+        let cursor = self.cursor_position();
+        match self.undo.undo(&mut self.editor_rows, cursor) {
+            Some(cursor) => self.restore(cursor),
+            None => self.status_message.set_message("Nothing to undo".into()),
+        }
+    }
+
+    pub fn redo(&mut self) {
+        let cursor = self.cursor_position();
+        match self.undo.redo(&mut self.editor_rows, cursor) {
+            Some(cursor) => self.restore(cursor),
+            None => self.status_message.set_message("Nothing to redo".into()),
+        }
+    }
+
+    // rows the line operations work on, the selected ones or the cursor row
+    fn line_range(&self) -> Range<usize> {
+        let cursor_y = self.cursor_controller.cursor_y;
+        let rows = self
+            .cursor_controller
+            .selected_rows()
+            .unwrap_or(cursor_y..cursor_y + 1);
+        rows.start..min(rows.end, self.editor_rows.num_rows())
+    }
+
+    // moves the cursor and anchor down by delta rows, up when negative
+    fn shift_y(&mut self, delta: isize) {
+        let cursor = &mut self.cursor_controller;
+        cursor.cursor_y = cursor.cursor_y.saturating_add_signed(delta);
+        if let Some((_, y)) = cursor.anchor.as_mut() {
+            *y = y.saturating_add_signed(delta);
+        }
+    }
+
+    /* @brief moves the cursor row or the selected rows up or down past the
+     *        row next to them, the selection moves along
+     */
+    pub fn move_lines(&mut self, direction: KeyCode) {
+        self.single_cursor();
+        let rows = self.line_range();
+        let up = direction == KeyCode::Up;
+        if rows.is_empty() || (up && rows.start == 0) || (!up && rows.end >= self.editor_rows.num_rows()) {
+            return;
+        }
+        self.record_edit(EditKind::Other);
+        if up {
+            self.editor_rows.move_row(rows.start - 1, rows.end - 1);
+            self.shift_y(-1);
+        } else {
+            self.editor_rows.move_row(rows.end, rows.start);
+            self.shift_y(1);
+        }
+        self.dirty += 1;
+    }
+
+    // copies the cursor row or the selected rows below themselves, the cursor moves to the copy
+    pub fn duplicate_lines(&mut self) {
+        self.single_cursor();
+        let rows = self.line_range();
+        if rows.is_empty() {
+            return;
+        }
+        self.record_edit(EditKind::Other);
+        for (idx, at) in rows.clone().enumerate() {
+            let content = self.editor_rows.get_row(at).row_content.clone();
+            self.editor_rows.insert_row_at(rows.end + idx);
+            self.editor_rows.insert_str(rows.end + idx, 0, &content);
+        }
+        self.shift_y(rows.len() as isize);
+        self.dirty += 1;
+    }
+
+    pub fn delete_lines(&mut self) {
+        self.single_cursor();
+        let rows = self.line_range();
+        if rows.is_empty() {
+            return;
+        }
+        self.record_edit(EditKind::Other);
+        rows.clone().rev().for_each(|at| self.editor_rows.delete_row(at));
+        self.cursor_controller.anchor = None;
+        self.cursor_controller.cursor_y = rows.start;
+        self.cursor_controller.clamp_x(&self.editor_rows);
+        self.dirty += 1;
+    }
+
+    /* @brief joins the selected rows or the cursor row and the one below
+     *        into one row, the whitespace at each join becomes a single space
+     */
+    pub fn join_lines(&mut self) {
+        self.single_cursor();
+        let rows = self.line_range();
+        let joins = rows.len().max(2) - 1;
+        if rows.is_empty() || rows.start + joins >= self.editor_rows.num_rows() {
+            return;
+        }
+        self.record_edit(EditKind::Other);
+        let at = rows.start;
+        let mut join_x = 0;
+        for _ in 0..joins {
+            let next = self.editor_rows.get_row(at + 1).row_content.trim_start().to_string();
+            let row = &mut self.editor_rows.get_row_mut(at).row_content;
+            row.truncate(row.trim_end().len());
+            join_x = row.len();
+            if !row.is_empty() && !next.is_empty() {
+                row.push(' ');
+            }
+            row.push_str(&next);
+            self.editor_rows.delete_row(at + 1);
+        }
+        self.cursor_controller.anchor = None;
+        self.cursor_controller.cursor_y = at;
+        self.cursor_controller.cursor_x = join_x;
+        self.dirty += 1;
+    }
+
+    // inserts an empty row below or above the cursor row, the cursor stays where it is
+    pub fn insert_blank_line(&mut self, direction: KeyCode) {
+        self.single_cursor();
+        self.record_edit(EditKind::Other);
+        let cursor_y = self.cursor_controller.cursor_y;
+        let at = if direction == KeyCode::Up {
+            cursor_y
+        } else {
+            min(cursor_y + 1, self.editor_rows.num_rows())
+        };
+        self.editor_rows.insert_row_at(at);
+        if direction == KeyCode::Up {
+            self.shift_y(1);
+        }
+        self.dirty += 1;
+    }
+
+    /* @brief comments out the cursor row or the selected rows, or
+     *        uncomments them when every non blank one already is, the marker
+     *        goes at the smallest indentation among the rows, filetypes
+     *        without line comments wrap each row in a block comment
+     */
+    pub fn toggle_comment(&mut self) {
+        self.single_cursor();
+        let filetype = self.editor_rows.filetype();
+        let Some((start, end)) = filetype
+            .line_comment
+            .map(|start| (start, ""))
+            .or(filetype.block_comment)
+        else {
+            self.status_message
+                .set_message(format!("No comments for {} files", filetype.name));
+            return;
+        };
+        let rows: Vec<usize> = self
+            .line_range()
+            .filter(|at| !self.editor_rows.get_row(*at).row_content.trim().is_empty())
+            .collect();
+        let Some(indent) = rows
+            .iter()
+            .map(|at| {
+                let row = &self.editor_rows.get_row(*at).row_content;
+                row.len() - row.trim_start_matches([' ', '\t']).len()
+            })
+            .min()
+        else {
+            return;
+        };
+        let commented = rows.iter().all(|at| {
+            let row = self.editor_rows.get_row(*at).row_content.trim();
+            row.starts_with(start) && row.ends_with(end) && row.len() >= start.len() + end.len()
+        });
+        self.record_edit(EditKind::Other);
+        for at in rows {
+            if commented {
+                self.remove_comment(at, start, end);
+            } else {
+                if !end.is_empty() {
+                    let len = self.editor_rows.get_row(at).row_content.len();
+                    self.editor_rows.insert_str(at, len, &format!(" {}", end));
+                }
+                self.insert_marker(at, indent, &format!("{} ", start));
+            }
+        }
+        self.dirty += 1;
+    }
+
+    /* @brief wraps the selection in the filetype's block comment, or the
+     *        cursor row from its indentation without a selection, unwrapping
+     *        it instead when it already is a block comment
+     */
+    pub fn toggle_block_comment(&mut self) {
+        self.single_cursor();
+        let filetype = self.editor_rows.filetype();
+        let Some((start, end)) = filetype.block_comment else {
+            self.status_message
+                .set_message(format!("No block comments for {} files", filetype.name));
+            return;
+        };
+        let cursor_y = self.cursor_controller.cursor_y;
+        if cursor_y >= self.editor_rows.num_rows() && self.cursor_controller.selection().is_none() {
+            return;
+        }
+        let ((start_x, start_y), (end_x, end_y)) = self.cursor_controller.selection().unwrap_or_else(|| {
+            let row = &self.editor_rows.get_row(cursor_y).row_content;
+            let indent = row.len() - row.trim_start_matches([' ', '\t']).len();
+            ((indent, cursor_y), (row.trim_end().len().max(indent), cursor_y))
+        });
+        let end_y = min(end_y, self.editor_rows.num_rows() - 1);
+        let end_x = min(end_x, self.editor_rows.get_row(end_y).row_content.len());
+        let first = &self.editor_rows.get_row(start_y).row_content[start_x..];
+        let last = &self.editor_rows.get_row(end_y).row_content[..end_x];
+        let commented = first.starts_with(start)
+            && last.ends_with(end)
+            && (start_y < end_y || end_x - start_x >= start.len() + end.len());
+        self.record_edit(EditKind::Other);
+        if commented {
+            let mut from = end_x - end.len();
+            if self.editor_rows.get_row(end_y).row_content[..from].ends_with(' ') {
+                from -= 1;
+            }
+            self.editor_rows.delete_range(end_y, from, end_x);
+            self.shift_x(end_y, from, -((end_x - from) as isize));
+            let first = &self.editor_rows.get_row(start_y).row_content[start_x + start.len()..];
+            let len = start.len() + usize::from(first.starts_with(' '));
+            self.editor_rows.delete_range(start_y, start_x, start_x + len);
+            self.shift_x(start_y, start_x, -(len as isize));
+        } else {
+            self.editor_rows.insert_str(end_y, end_x, &format!(" {}", end));
+            self.shift_x(end_y, end_x, end.len() as isize + 1);
+            self.insert_marker(start_y, start_x, &format!("{} ", start));
+        }
+        self.dirty += 1;
+    }
+
+    // inserts a comment marker at byte at_x, positions after it move along
+    fn insert_marker(&mut self, at: usize, at_x: usize, marker: &str) {
+        self.editor_rows.insert_str(at, at_x, marker);
+        self.shift_x(at, at_x, marker.len() as isize);
+    }
+
+    // removes the line comment markers of row at along with the space next to them
+    fn remove_comment(&mut self, at: usize, start: &str, end: &str) {
+        let row = &self.editor_rows.get_row(at).row_content;
+        if !end.is_empty() {
+            let to = row.trim_end().len();
+            let mut from = to - end.len();
+            if row[..from].ends_with(' ') {
+                from -= 1;
+            }
+            self.editor_rows.delete_range(at, from, to);
+        }
+        let row = &self.editor_rows.get_row(at).row_content;
+        let from = row.len() - row.trim_start_matches([' ', '\t']).len();
+        let len = start.len() + usize::from(row[from + start.len()..].starts_with(' '));
+        self.editor_rows.delete_range(at, from, from + len);
+        self.shift_x(at, from, -(len as isize));
+    }
+
+    /* @brief shows the prompt template on the status bar with the visible
+     *        part of the input and the note substituted for {} followed by
+     *        the hint and places the terminal cursor at the input cursor
+     */
+    pub fn show_prompt(&mut self, template: &str, input: &mut InputLine, hint: &str) {
+        let (before, after) = template.split_once("{}").unwrap_or((template, ""));
+        let note = &self.prompt_note;
+        let width = self.size.0.saturating_sub(before.width() + after.width() + note.width() + 1);
+        let (visible, cursor) = input.view(width);
+        self.status_message
+            .set_message(format!("{}{}{}{}{}", before, visible, note, after, hint));
+        self.prompt_cursor = Some(min(before.width() + cursor, self.size.0.saturating_sub(1)));
+    }
+
+    pub fn close_prompt(&mut self) {
+        self.prompt_cursor = None;
+        self.prompt_note.clear();
+    }
+
+    /* @brief where keyword starts on row at, with the case mode of the
+     *        search and within its bounds when it has any
+     */
+    fn row_matches(&self, at: usize, keyword: &str) -> Vec<usize> {
+        let ignore_case = self.search_index.case_mode.ignores_case(keyword);
+        let bounds = self.search_index.bounds;
+        let row = &self.editor_rows.get_row(at).row_content;
+        search::find_all(row, keyword, ignore_case)
+            .into_iter()
+            .filter(|found| {
+                bounds.is_none_or(|(start, end)| {
+                    (at, found.start) >= (start.1, start.0) && (at, found.end) <= (end.1, end.0)
+                })
+            })
+            .map(|found| found.start)
+            .collect()
+    }
+
+    /* @brief the match of keyword nearest cursor going in direction and
+     *        whether the search wrapped around the end of the buffer to
+     *        get there
+     */
+    fn next_match(
+        &self,
+        keyword: &str,
+        cursor: (usize, usize),
+        direction: Option<SearchDirection>,
+    ) -> Option<((usize, usize), bool)> {
+        if keyword.is_empty() {
+            return None;
+        }
+        let num_rows = self.editor_rows.num_rows();
+        search::nearest_match(num_rows, cursor, direction, |at| self.row_matches(at, keyword))
+    }
+
+    fn find_callback(output: &mut Output, keyword: &str, key_code: Option<KeyCode>) {
+        match key_code {
+            Some(KeyCode::Esc) => {
+                output.search_index.reset();
+            },
+            Some(KeyCode::Enter) => {
+                output.search_index.reset();
+                output.search_index.keyword = Some(keyword.to_string());
+            },
+            Some(key_code) => {
+                // Down/Up step to the next/previous match, any other key means
+                //  the keyword changed so search again from the current match,
+                //  which starts out at the cursor
+                output.search_index.direction = match key_code {
+                    KeyCode::Down => SearchDirection::Forward.into(),
+                    KeyCode::Up => SearchDirection::Backward.into(),
+                    _ => None,
+                };
+                if let Some(direction) = output.search_index.direction {
+                    output.search_index.last_direction = direction;
+                }
+                let from = (output.search_index.x_index, output.search_index.y_index);
+                let found = output.next_match(keyword, from, output.search_index.direction);
+                output.prompt_note = output.search_note(match found {
+                    Some((_, true)) => " -- search wrapped",
+                    Some(_) => "",
+                    None if keyword.is_empty() => "",
+                    None => " -- no matches",
+                });
+                // a wrapped search ends macro playback too, or repeating one would never stop
+                output.search_failed = found.is_none_or(|(_, wrapped)| wrapped);
+                if let Some(((x, y), _)) = found {
+                    output.cursor_controller.cursor_y = y;
+                    output.cursor_controller.cursor_x = x;
+                    output.search_index.y_index = y;
+                    output.search_index.x_index = x;
+                }
+            }
+            None => (),
+        }
+    }
+
+    // the search options followed by status, shown after the search prompt
+    fn search_note(&self, status: &str) -> String {
+        let within = if self.search_index.bounds.is_some() { ", in selection" } else { "" };
+        format!(" [{}{}]{}", self.search_index.case_mode.label(), within, status)
+    }
+
+    // switches to the next case mode while the search prompt is open
+    pub fn toggle_case_mode(&mut self) {
+        self.search_index.case_mode = self.search_index.case_mode.next();
+        self.prompt_note = self.search_note("");
+    }
+
+    /* @brief repeats the last search after its prompt was closed, in the
+     *        direction it last stepped or the other one with reverse
+     */
+    pub fn search_next(&mut self, reverse: bool) {
+        let Some(keyword) = self.search_index.keyword.clone() else {
+            self.status_message.set_message("Nothing searched yet".into());
+            return;
+        };
+        self.single_cursor();
+        self.undo.seal();
+        self.cursor_controller.anchor = None;
+        let direction = if reverse {
+            self.search_index.last_direction.reverse()
+        } else {
+            self.search_index.last_direction
+        };
+        let found = self.next_match(&keyword, self.cursor_position(), Some(direction));
+        self.search_failed = found.is_none_or(|(_, wrapped)| wrapped);
+        match found {
+            Some(((x, y), wrapped)) => {
+                (self.cursor_controller.cursor_x, self.cursor_controller.cursor_y) = (x, y);
+                if wrapped {
+                    self.status_message.set_message("Search wrapped".into());
+                }
+            }
+            None => self.status_message.set_message(format!("No matches for {}", keyword)),
+        }
+    }
+
+    pub fn find(&mut self) -> std::io::Result<()> {
+        self.single_cursor();
+        self.undo.seal();
+        // a selection limits the search to itself, which starts at its beginning
+        let res = self.cursor_controller.clone();
+        self.search_index.bounds = self.cursor_controller.selection();
+        self.cursor_controller.anchor = None;
+        let (x, y) = self.search_index.bounds.map_or(self.cursor_position(), |(start, _)| start);
+        self.search_index.y_index = y;
+        self.search_index.x_index = x;
+        self.search_index.last_direction = SearchDirection::Forward;
+        self.prompt_note = self.search_note("");
+        match prompt!(
+            self,
+            PromptKind::Search,
+            "Search: {} (CTRL + n/p: next/prev, ALT + c: case, ESC: cancel)",
+            Output::find_callback
+        ) {
+            Some(keyword) => {
+                let clip = Clip {
+                    text: keyword,
+                    block: false,
+                };
+                self.registers.set(SEARCH_REGISTER, clip);
+                self.save_registers();
+            }
+            None => self.cursor_controller = res,
+        };
+        Ok(())
+    }
+
+    /* @brief asks for a replace command and runs it on the rows it names,
+     *        or the selection or else the cursor row without a range, the
+     *        selection stays around the same text as lengths change
+     */
+    pub fn substitute(&mut self) -> crossterm::Result<()> {
+        self.single_cursor();
+        self.undo.seal();
+        let Some(input) = prompt!(
+            self,
+            PromptKind::Replace,
+            "Replace: {} (e.g. s/old/new/g, 10,40s/old/new/, %s/old/new/i)"
+        ) else {
+            return Ok(());
+        };
+        let cursor_y = self.cursor_controller.cursor_y;
+        let num_rows = self.editor_rows.num_rows();
+        let command = match Substitute::parse(&input, cursor_y, num_rows) {
+            Ok(command) => command,
+            Err(err) => {
+                self.status_message.set_message(err);
+                return Ok(());
+            }
+        };
+        let (start, end) = match (&command.rows, self.cursor_controller.selection()) {
+            // % on an empty buffer has no rows to look in
+            (Some(rows), _) if rows.is_empty() => {
+                self.status_message
+                    .set_message(format!("No matches for {}", command.pattern));
+                return Ok(());
+            }
+            (Some(rows), _) => ((0, rows.start), (usize::MAX, rows.end - 1)),
+            (None, Some(selection)) => selection,
+            (None, None) => ((0, cursor_y), (usize::MAX, cursor_y)),
+        };
+        let ignore_case = command
+            .ignore_case
+            .unwrap_or_else(|| self.search_index.case_mode.ignores_case(&command.pattern));
+        let replacement = &command.replacement;
+        self.record_edit(EditKind::Other);
+        let (mut count, mut rows) = (0, 0);
+        for at in start.1..min(end.1 + 1, num_rows) {
+            let row = &self.editor_rows.get_row(at).row_content;
+            let from = if at == start.1 { start.0 } else { 0 };
+            let to = if at == end.1 { min(end.0, row.len()) } else { row.len() };
+            let mut found: Vec<Range<usize>> = search::find_all(row, &command.pattern, ignore_case)
+                .into_iter()
+                .filter(|found| found.start >= from && found.end <= to)
+                .collect();
+            if !command.global {
+                found.truncate(1);
+            }
+            if found.is_empty() {
+                continue;
+            }
+            rows += 1;
+            count += found.len();
+            // from the last match back, so the earlier ones stay where they were
+            for found in found.into_iter().rev() {
+                self.editor_rows.insert_str(at, found.end, replacement);
+                self.shift_x(at, found.end, replacement.len() as isize);
+                self.editor_rows.delete_range(at, found.start, found.end);
+                self.shift_x(at, found.start, -(found.len() as isize));
+            }
+        }
+        if count == 0 {
+            self.status_message
+                .set_message(format!("No matches for {}", command.pattern));
+            return Ok(());
+        }
+        self.dirty += 1;
+        self.status_message
+            .set_message(format!("Replaced {} occurrences on {} rows", count, rows));
+        Ok(())
+    }
+
+    // puts background on screen, returning the buffer that was there
+    fn swap_in(&mut self, background: Background) -> Background {
+        self.single_cursor();
+        Background {
+            editor_rows: mem::replace(&mut self.editor_rows, background.editor_rows),
+            cursor_controller: mem::replace(&mut self.cursor_controller, background.cursor_controller),
+            undo: mem::replace(&mut self.undo, background.undo),
+            dirty: mem::replace(&mut self.dirty, background.dirty),
+            list: mem::replace(&mut self.list, background.list),
+        }
+    }
+
+    // shows background, the buffer on screen goes last among the others
+    fn show_buffer(&mut self, background: Background) {
+        let previous = self.swap_in(background);
+        if !previous.is_unused() {
+            self.buffers.push_back(previous);
+        }
+    }
+
+    // switches to the next buffer, or the previous one for Left
+    pub fn switch_buffer(&mut self, direction: KeyCode) {
+        let next = if direction == KeyCode::Left {
+            self.buffers.pop_back()
+        } else {
+            self.buffers.pop_front()
+        };
+        let Some(next) = next else {
+            self.status_message.set_message("No other buffers".into());
+            return;
+        };
+        let previous = self.swap_in(next);
+        if direction == KeyCode::Left {
+            self.buffers.push_front(previous);
+        } else {
+            self.buffers.push_back(previous);
+        }
+    }
+
+    // closes the buffer on screen unless it has unsaved changes, the next one takes its place
+    pub fn close_buffer(&mut self) {
+        if self.dirty > 0 {
+            self.status_message
+                .set_message("Modified! save or undo the changes before closing".into());
+            return;
+        }
+        match self.buffers.pop_front() {
+            Some(next) => {
+                self.swap_in(next);
+            }
+            None => self.status_message.set_message("No other buffers".into()),
+        }
+    }
+
+    // whether any open buffer has unsaved changes
+    pub fn is_modified(&self) -> bool {
+        self.dirty > 0 || self.buffers.iter().any(|buffer| buffer.dirty > 0)
+    }
+
+    pub fn in_list(&self) -> bool {
+        self.list.is_some()
+    }
+
+    // shows the buffer of path, loading the file unless it is open already
+    pub fn open_file(&mut self, path: &Path) -> bool {
+        let on_screen = self
+            .editor_rows
+            .filename
+            .as_deref()
+            .is_some_and(|name| buffers::same_file(name, path));
+        if on_screen {
+            return true;
+        }
+        if let Some(idx) = self.buffers.iter().position(|buffer| buffer.is_file(path)) {
+            let background = self.buffers.remove(idx).unwrap();
+            self.show_buffer(background);
+            return true;
+        }
+        match EditorRows::open(path.to_path_buf(), &self.config) {
+            Ok(editor_rows) => {
+                self.show_buffer(Background::new(editor_rows, None, self.size));
+                true
+            }
+            Err(err) => {
+                self.status_message
+                    .set_message(format!("Error opening {} {:?}", path.display(), err.to_string()));
+                false
+            }
+        }
+    }
+
+    // the rows of every open buffer, the one on screen first
+    fn open_rows(&self) -> Vec<&EditorRows> {
+        let others = self.buffers.iter().map(|buffer| &buffer.editor_rows);
+        std::iter::once(&self.editor_rows).chain(others).collect()
+    }
+
+    // the hit on the cursor row of the list buffer
+    fn list_hit(&self) -> Option<&Hit> {
+        let at = self.cursor_controller.cursor_y;
+        match self.list.as_ref()? {
+            // the first row is the heading
+            List::Grep(hits) => hits.get(at.checked_sub(1)?),
+            List::Replace(changes, rows) => rows.get(at).copied().flatten().map(|idx| &changes[idx].hit),
+        }
+    }
+
+    // opens the entry of the list buffer on the cursor row
+    pub fn open_list_entry(&mut self) {
+        let Some(hit) = self.list_hit().cloned() else {
+            return;
+        };
+        // selects the match, the cursor on its start
+        if self.open_file(&hit.path) {
+            let cursor = &mut self.cursor_controller;
+            cursor.cursor_y = min(hit.line, self.editor_rows.num_rows());
+            cursor.cursor_x = hit.end;
+            cursor.clamp_x(&self.editor_rows);
+            cursor.anchor = Some((cursor.cursor_x, cursor.cursor_y));
+            cursor.cursor_x = hit.start;
+            cursor.clamp_x(&self.editor_rows);
+        }
+    }
+
+    // the project search options, shown after the prompt input
+    fn grep_note(&self) -> String {
+        let mode = if self.grep_regex { "regex" } else { "literal" };
+        format!(" [{}, {}]", mode, self.search_index.case_mode.label())
+    }
+
+    fn grep_callback(output: &mut Output, _: &str, _: Option<KeyCode>) {
+        output.prompt_note = output.grep_note();
+    }
+
+    /* @brief asks for a pattern and lists the lines matching it in the text
+     *        files under the working directory in a list buffer, Enter on
+     *        one of them opens its file at the match
+     */
+    pub fn grep(&mut self) -> crossterm::Result<()> {
+        self.prompt_note = self.grep_note();
+        let Some(pattern) = prompt!(
+            self,
+            PromptKind::Grep,
+            "Find in files: {} (ALT + r: regex, ALT + c: case, ESC: cancel)",
+            Output::grep_callback
+        ) else {
+            return Ok(());
+        };
+        let ignore_case = self.search_index.case_mode.ignores_case(&pattern);
+        let matcher = match Matcher::new(&pattern, self.grep_regex, ignore_case) {
+            Ok(matcher) => matcher,
+            Err(err) => {
+                self.status_message.set_message(format!("Invalid regex: {}", err));
+                return Ok(());
+            }
+        };
+        let (hits, capped) = grep::search(&env::current_dir()?, &matcher, &self.open_rows());
+        if hits.is_empty() {
+            self.status_message.set_message(format!("No matches for {}", pattern));
+            return Ok(());
+        }
+        let files = 1 + hits.windows(2).filter(|pair| pair[0].path != pair[1].path).count();
+        let mut rows = vec![format!(
+            "{}{} hits for {} in {} files (Enter: open, ALT + ,/.: previous/next buffer)",
+            if capped { "first " } else { "" },
+            hits.len(),
+            pattern,
+            files,
+        )];
+        rows.extend(hits.iter().map(|hit| {
+            let col = hit.text[..hit.start].chars().count() + 1;
+            format!("{}:{}:{}: {}", hit.path.display(), hit.line + 1, col, hit.text)
+        }));
+        let editor_rows = EditorRows::scratch(rows, &self.config);
+        self.show_buffer(Background::new(editor_rows, Some(List::Grep(hits)), self.size));
+        self.cursor_controller.cursor_y = 1;
+        Ok(())
+    }
+
+    /* @brief asks for a replace command like the one for the buffer and
+     *        previews the changes it makes to the text files under the
+     *        working directory, grouped by file, in a list buffer where Space
+     *        deselects a change and CTRL + s applies the selected ones
+     */
+    pub fn grep_replace(&mut self) -> crossterm::Result<()> {
+        self.prompt_note = self.grep_note();
+        let Some(input) = prompt!(
+            self,
+            PromptKind::GrepReplace,
+            "Replace in files: {} (e.g. s/old/new/g, ALT + r: regex, ALT + c: case)",
+            Output::grep_callback
+        ) else {
+            return Ok(());
+        };
+        let command = match Substitute::parse(&input, 0, 0) {
+            Ok(command) if command.rows.is_some() => {
+                self.status_message.set_message("Row ranges don't apply across files".into());
+                return Ok(());
+            }
+            Ok(command) => command,
+            Err(err) => {
+                self.status_message.set_message(err);
+                return Ok(());
+            }
+        };
+        let ignore_case = command
+            .ignore_case
+            .unwrap_or_else(|| self.search_index.case_mode.ignores_case(&command.pattern));
+        let matcher = match Matcher::new(&command.pattern, self.grep_regex, ignore_case) {
+            Ok(matcher) => matcher,
+            Err(err) => {
+                self.status_message.set_message(format!("Invalid regex: {}", err));
+                return Ok(());
+            }
+        };
+        let (mut hits, capped) = grep::search(&env::current_dir()?, &matcher, &self.open_rows());
+        // like s/// without g only the first match on a line is replaced
+        if !command.global {
+            hits.dedup_by(|hit, prev| hit.path == prev.path && hit.line == prev.line);
+        }
+        if hits.is_empty() {
+            self.status_message
+                .set_message(format!("No matches for {}", command.pattern));
+            return Ok(());
+        }
+        let changes: Vec<Change> = hits
+            .into_iter()
+            .map(|hit| Change {
+                replacement: matcher.replacement(&hit, &command.replacement),
+                hit,
+                selected: true,
+            })
+            .collect();
+        let (mut rows, mut entries) = (Vec::new(), Vec::new());
+        for (idx, change) in changes.iter().enumerate() {
+            let hit = &change.hit;
+            if idx == 0 || changes[idx - 1].hit.path != hit.path {
+                rows.push(hit.path.display().to_string());
+                entries.push(None);
+            }
+            let col = hit.text[..hit.start].chars().count() + 1;
+            rows.push(format!(
+                "  [x] {}:{}: {} => {}",
+                hit.line + 1,
+                col,
+                hit.text.trim(),
+                change.after().trim()
+            ));
+            entries.push(Some(idx));
+        }
+        rows.insert(0, format!(
+            "{}{} changes in {} files (Space: select, Enter: open, CTRL + s: apply)",
+            if capped { "first " } else { "" },
+            changes.len(),
+            entries.iter().filter(|entry| entry.is_none()).count(),
+        ));
+        entries.insert(0, None);
+        let editor_rows = EditorRows::scratch(rows, &self.config);
+        self.show_buffer(Background::new(editor_rows, Some(List::Replace(changes, entries)), self.size));
+        self.cursor_controller.cursor_y = 2;
+        Ok(())
+    }
+
+    /* @brief selects or deselects the change on the cursor row of a replace
+     *        preview, on the heading of a file its changes all follow the
+     *        first one
+     */
+    pub fn toggle_list_entry(&mut self) {
+        let Some(List::Replace(changes, entries)) = &mut self.list else {
+            return;
+        };
+        let at = self.cursor_controller.cursor_y;
+        let group: Vec<usize> = match entries.get(at) {
+            Some(Some(_)) => vec![at],
+            Some(None) => (at + 1..entries.len()).take_while(|row| entries[*row].is_some()).collect(),
+            None => return,
+        };
+        let Some(first) = group.first().and_then(|row| entries[*row]) else {
+            return;
+        };
+        let selected = !changes[first].selected;
+        for row in group {
+            let Some(idx) = entries[row] else {
+                continue;
+            };
+            changes[idx].selected = selected;
+            // the mark between the brackets of "  [x] "
+            self.editor_rows.delete_range(row, 3, 4);
+            self.editor_rows.insert_str(row, 3, if selected { "x" } else { " " });
+        }
+    }
+
+    /* @brief applies the selected changes of the replace preview and closes
+     *        it, open buffers are changed in memory and left unsaved while
+     *        other files are rewritten on disk, a line that changed since
+     *        the search is skipped
+     */
+    pub fn apply_replace(&mut self) {
+        let changes = match self.list.take() {
+            Some(List::Replace(changes, _)) => changes,
+            list => {
+                self.list = list;
+                return;
+            }
+        };
+        let selected: Vec<&Change> = changes.iter().filter(|change| change.selected).collect();
+        let (mut applied, mut files, mut unwritten, mut failed) = (0, 0, 0, Vec::new());
+        for file in selected.chunk_by(|a, b| a.hit.path == b.hit.path) {
+            let path = &file[0].hit.path;
+            let count = match self.buffers.iter_mut().find(|buffer| buffer.is_file(path)) {
+                Some(buffer) => {
+                    let cursor = &mut buffer.cursor_controller;
+                    buffer.undo.record(EditKind::Other, &buffer.editor_rows, (cursor.cursor_x, cursor.cursor_y));
+                    let count = grep::replace_rows(&mut buffer.editor_rows, file);
+                    if count > 0 {
+                        buffer.dirty += 1;
+                        cursor.anchor = None;
+                        cursor.clamp_x(&buffer.editor_rows);
+                    }
+                    count
+                }
+                None => match grep::replace_on_disk(path, file) {
+                    Ok(count) => count,
+                    Err(err) => {
+                        failed.push(format!("{} {:?}", path.display(), err.to_string()));
+                        unwritten += file.len();
+                        continue;
+                    }
+                },
+            };
+            applied += count;
+            files += usize::from(count > 0);
+        }
+        let next = self.buffers.pop_front().unwrap_or_else(|| {
+            Background::new(EditorRows::scratch(Vec::new(), &self.config), None, self.size)
+        });
+        self.swap_in(next);
+        let mut summary = format!("Replaced {} occurrences in {} files", applied, files);
+        let skipped = selected.len() - applied - unwritten;
+        if skipped > 0 {
+            summary.push_str(&format!(", {} skipped on lines changed since the search", skipped));
+        }
+        if !failed.is_empty() {
+            summary.push_str(&format!(", error writing {}", failed.join(", ")));
+        }
+        self.status_message.set_message(summary);
+    }
+
+    pub fn finder_has_selection(&self) -> bool {
+        self.finder.as_ref().is_some_and(|finder| finder.selection().is_some())
+    }
+
+    fn find_file_callback(output: &mut Output, query: &str, key_code: Option<KeyCode>) {
+        let Some(finder) = output.finder.as_mut() else {
+            return;
+        };
+        match key_code {
+            Some(KeyCode::Down) => finder.step(false),
+            Some(KeyCode::Up) => finder.step(true),
+            Some(KeyCode::Esc) => {
+                output.finder = None;
+                return;
+            }
+            Some(KeyCode::Enter) | None => (),
+            Some(_) => finder.update(query),
+        }
+        let (found, total) = finder.counts();
+        output.prompt_note = format!(" [{}/{}]", found, total);
+    }
+
+    /* @brief lists the files under the working directory that ignore files
+     *        don't leave out over the last rows, narrowed down and ranked as
+     *        the query is typed, Enter opens the selected one even when
+     *        nothing was typed
+     */
+    pub fn find_file(&mut self) -> crossterm::Result<()> {
+        let finder = Finder::new(grep::project_files(&env::current_dir()?));
+        let (found, total) = finder.counts();
+        self.prompt_note = format!(" [{}/{}]", found, total);
+        self.finder = Some(finder);
+        prompt!(
+            self,
+            PromptKind::FindFile,
+            "Open: {} (CTRL + n/p: next/previous, ESC: cancel)",
+            Output::find_file_callback
+        );
+        // the finder is gone when the prompt was cancelled
+        let Some(finder) = self.finder.take() else {
+            return Ok(());
+        };
+        match finder.selection() {
+            Some(file) => {
+                self.open_file(Path::new(file));
+            }
+            None => self.status_message.set_message("No matching files".into()),
+        }
+        Ok(())
+    }
+}
+
+#[macro_export]
+macro_rules! prompt {
+    ($output:expr, $kind:expr, $args:tt) => {
+        prompt!($output, $kind, $args, |&_, _, _| {}) // this ignores the callback when called
+                                                          // with 3 arguments
+    };
+    ($output:expr, $kind:expr, $args:tt, $callback:expr) => {{
+        let output:&mut Output = $output;
+        let kind: PromptKind = $kind;
+        let mut input = InputLine::new();
+        // position in the history while browsing with Up/Down, and what was
+        //  typed before browsing started
+        let mut history_pos: Option<usize> = None;
+        let mut draft = String::new();
+        let mut completer = PathCompleter::new();
+        loop {
+            output.show_prompt($args, &mut input, &completer.hint());
+            output.refresh()?;
+            let event = output.reader.read_event();
+            if !matches!(event, None | Some(Event::Key(event::KeyEvent { code: KeyCode::Tab, .. }))) {
+                completer.reset();
+            }
+            // the callback is only told about keys that change the input,
+            //  cursor movement inside the input is handled here
+            let key_code = match event {
+                Some(Event::Paste(text)) => {
+                    input.insert_str(&text);
+                    // pasting is reported as typing
+                    Some(KeyCode::Null)
+                },
+                Some(Event::Key(key_event)) => match key_event {
+                    event::KeyEvent {
+                        code: KeyCode::Enter,
+                        modifiers: KeyModifiers::NONE,
+                        ..
+                    } => {
+                        if !input.is_empty() {
+                            output.status_message.set_message(String::new());
+                            output.prompt_history.add(kind, input.as_str());
+                            if let Err(err) = output.prompt_history.save() {
+                                output.status_message.set_message(format!("Error saving history {:?}", err.to_string()));
+                            }
+                            $callback(output, input.as_str(), Some(KeyCode::Enter));
+                            break;
+                        }
+                        // the file finder has a selection to open before anything is typed
+                        if kind == PromptKind::FindFile && output.finder_has_selection() {
+                            output.status_message.set_message(String::new());
+                            $callback(output, input.as_str(), Some(KeyCode::Enter));
+                            break;
+                        }
+                        None
+                    },
+                    event::KeyEvent {
+                        code: KeyCode::Esc,
+                        ..
+                    } => {
+                        input.clear();
+                        output.status_message.clear_custom_message();
+                        $callback(output, input.as_str(), Some(KeyCode::Esc));
+                        break;
+                    },
+                    event::KeyEvent {
+                        code: code @ (KeyCode::Backspace | KeyCode::Delete),
+                        modifiers: KeyModifiers::NONE,
+                        ..
+                    } => {
+                        if code == KeyCode::Backspace { input.backspace() } else { input.delete() }
+                        Some(code)
+                    },
+                    event::KeyEvent {
+                        code: KeyCode::Char('w'),
+                        modifiers: KeyModifiers::CONTROL,
+                        ..
+                    } => {
+                        input.delete_word();
+                        Some(KeyCode::Backspace)
+                    },
+                    event::KeyEvent {
+                        code: KeyCode::Char('u'),
+                        modifiers: KeyModifiers::CONTROL,
+                        ..
+                    } => {
+                        input.delete_to_start();
+                        Some(KeyCode::Backspace)
+                    },
+                    event::KeyEvent {
+                        code: code @ (KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End),
+                        modifiers: KeyModifiers::NONE,
+                        ..
+                    } => {
+                        match code {
+                            KeyCode::Left => input.move_left(),
+                            KeyCode::Right => input.move_right(),
+                            KeyCode::Home => input.move_home(),
+                            _ => input.move_end(),
+                        }
+                        None
+                    },
+                    event::KeyEvent {
+                        code: code @ (KeyCode::Up | KeyCode::Down),
+                        modifiers: KeyModifiers::NONE,
+                        ..
+                    } => {
+                        let pos = match (code, history_pos) {
+                            (KeyCode::Up, None) => Some(0),
+                            (KeyCode::Up, Some(pos)) => Some(pos + 1),
+                            (_, Some(pos)) => pos.checked_sub(1),
+                            (_, None) => None,
+                        };
+                        match pos.map(|pos| (pos, output.prompt_history.get(kind, pos))) {
+                            Some((pos, Some(entry))) => {
+                                if history_pos.is_none() {
+                                    draft = input.as_str().to_string();
+                                }
+                                input.set(entry);
+                                history_pos = Some(pos);
+                                Some(KeyCode::Null)
+                            },
+                            // moved below the newest entry, restore what was typed
+                            None if history_pos.is_some() => {
+                                input.set(&draft);
+                                history_pos = None;
+                                Some(KeyCode::Null)
+                            },
+                            _ => None,
+                        }
+                    },
+                    event::KeyEvent {
+                        code: KeyCode::Tab,
+                        modifiers: KeyModifiers::NONE,
+                        ..
+                    } if kind == PromptKind::File => {
+                        completer.complete(&mut input);
+                        Some(KeyCode::Tab)
+                    },
+                    // the search runs again in the new case mode like after typing
+                    event::KeyEvent {
+                        code: KeyCode::Char('c'),
+                        modifiers: KeyModifiers::ALT,
+                        ..
+                    } if matches!(kind, PromptKind::Search | PromptKind::Grep | PromptKind::GrepReplace) => {
+                        output.toggle_case_mode();
+                        Some(KeyCode::Null)
+                    },
+                    event::KeyEvent {
+                        code: KeyCode::Char('r'),
+                        modifiers: KeyModifiers::ALT,
+                        ..
+                    } if matches!(kind, PromptKind::Grep | PromptKind::GrepReplace) => {
+                        output.grep_regex = !output.grep_regex;
+                        Some(KeyCode::Null)
+                    },
+                    // Up/Down belong to the history, callbacks see CTRL + n/p in their place
+                    event::KeyEvent {
+                        code: KeyCode::Char(char @ ('n' | 'p')),
+                        modifiers: KeyModifiers::CONTROL,
+                        ..
+                    } => Some(if char == 'n' { KeyCode::Down } else { KeyCode::Up }),
+                    event::KeyEvent {
+                        code: KeyCode::Char(char),
+                        modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                        ..
+                    } => {
+                        input.insert_char(char);
+                        Some(KeyCode::Char(char))
+                    },
+                    event::KeyEvent { code, .. } => Some(code),
+                },
+                _ => None,
+            };
+            $callback(output, input.as_str(), key_code);
         }
-        if input.is_empty() { None } else { Some(input) }
+        output.close_prompt();
+        if input.is_empty() { None } else { Some(input.as_str().to_string()) }
     }};
 
 }
@@ -1,21 +1,63 @@
+use std::collections::VecDeque;
 use std::time::Duration;
 
 use crossterm::event;
 use event::Event;
 
 
-pub struct Reader;
+pub struct Reader {
+    // events of a macro being played, handed out before the terminal is read
+    queue: VecDeque<Event>,
+    // events read from the terminal while a macro is being recorded
+    recording: Option<Vec<Event>>,
+}
 
 impl Reader {
-    // Reads one byte of input
+    pub fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+            recording: None,
+        }
+    }
+
+    // Reads one key press or bracketed paste
     // times out and does not block
-    pub fn read_keyevent(&self) -> Option<event::KeyEvent> {
+    pub fn read_event(&mut self) -> Option<Event> {
+            if let Some(event) = self.queue.pop_front() {
+                return Some(event);
+            }
             if event::poll(Duration::from_millis(16)).unwrap() {
-                if let Event::Key(event) = event::read().unwrap() {
+                if let event @ (Event::Key(_) | Event::Paste(_)) = event::read().unwrap() {
+                    if let Some(recording) = self.recording.as_mut() {
+                        recording.push(event.clone());
+                    }
                     return Some(event);
                 }
             }
-            return None
+            None
+    }
+
+    /* @brief queues events to be read ahead of the ones already queued, so
+     *        a macro played from a macro runs before the rest of it
+     */
+    pub fn play(&mut self, events: &[Event]) {
+        events.iter().rev().for_each(|event| self.queue.push_front(event.clone()));
     }
-}
 
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    // drops the next count queued events
+    pub fn skip(&mut self, count: usize) {
+        self.queue.drain(..count.min(self.queue.len()));
+    }
+
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Vec::new());
+    }
+
+    pub fn stop_recording(&mut self) -> Option<Vec<Event>> {
+        self.recording.take()
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::clipboard::Clip;
+use crate::config::config_dir;
+
+// copies and cuts the kill ring remembers, oldest are dropped first
+const KILL_RING_LIMIT: usize = 30;
+
+// the register holding the last copy
+pub const YANK_REGISTER: char = '0';
+// the register holding the last search
+pub const SEARCH_REGISTER: char = '/';
+
+/* @brief recent copies and cuts newest first along with the registers
+ *        a-z, the yank register 0 and the search register /, persisted as
+ *        `name<TAB>kind<TAB>text` lines where name is a register or ring,
+ *        kind is text or block and the text has \\ \t \n \r escaped
+ */
+pub struct Registers {
+    ring: VecDeque<Clip>,
+    registers: HashMap<char, Clip>,
+    path: Option<PathBuf>,
+}
+
+impl Registers {
+    pub fn load() -> Self {
+        let path = config_dir().map(|dir| dir.join("registers"));
+        let mut loaded = Self {
+            ring: VecDeque::new(),
+            registers: HashMap::new(),
+            path: None,
+        };
+        if let Some(content) = path.as_ref().and_then(|path| fs::read_to_string(path).ok()) {
+            for line in content.lines() {
+                let mut fields = line.splitn(3, '\t');
+                let (Some(name), Some(kind), Some(text)) = (fields.next(), fields.next(), fields.next()) else {
+                    continue;
+                };
+                let clip = Clip {
+                    text: unescape(text),
+                    block: kind == "block",
+                };
+                let mut chars = name.chars();
+                match (name, chars.next(), chars.next()) {
+                    ("ring", _, _) => loaded.ring.push_back(clip),
+                    (_, Some(register), None) if is_register(register) => {
+                        loaded.registers.insert(register, clip);
+                    }
+                    _ => (),
+                }
+            }
+        }
+        loaded.ring.truncate(KILL_RING_LIMIT);
+        loaded.path = path;
+        loaded
+    }
+
+    /* @brief remembers a copy or cut at the front of the ring, a copy also
+     *        goes into the yank register
+     */
+    pub fn kill(&mut self, clip: Clip, is_copy: bool) {
+        if is_copy {
+            self.registers.insert(YANK_REGISTER, clip.clone());
+        }
+        self.ring.retain(|it| it.text != clip.text || it.block != clip.block);
+        self.ring.push_front(clip);
+        self.ring.truncate(KILL_RING_LIMIT);
+    }
+
+    pub fn set(&mut self, register: char, clip: Clip) {
+        self.registers.insert(register, clip);
+    }
+
+    pub fn get(&self, register: char) -> Option<&Clip> {
+        self.registers.get(&register)
+    }
+
+    /* @brief everything there is to paste for the picker, the registers in
+     *        order followed by the ring from newest to oldest, each with a label
+     */
+    pub fn entries(&self) -> Vec<(String, Clip)> {
+        let mut names: Vec<&char> = self.registers.keys().collect();
+        names.sort();
+        let mut entries: Vec<(String, Clip)> = names
+            .into_iter()
+            .map(|name| (format!("@{}", name), self.registers[name].clone()))
+            .collect();
+        entries.extend(
+            self.ring
+                .iter()
+                .enumerate()
+                .map(|(idx, clip)| (format!("ring {}", idx + 1), clip.clone())),
+        );
+        entries
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let line = |name: &str, clip: &Clip| {
+            let kind = if clip.block { "block" } else { "text" };
+            format!("{}\t{}\t{}\n", name, kind, escape(&clip.text))
+        };
+        let mut names: Vec<&char> = self.registers.keys().collect();
+        names.sort();
+        let mut content: String = names
+            .into_iter()
+            .map(|name| line(&name.to_string(), &self.registers[name]))
+            .collect();
+        self.ring.iter().for_each(|clip| content.push_str(&line("ring", clip)));
+        fs::write(path, content)
+    }
+}
+
+pub fn is_register(name: char) -> bool {
+    name.is_ascii_lowercase() || name == YANK_REGISTER || name == SEARCH_REGISTER
+}
+
+fn escape(text: &str) -> String {
+    text.chars().fold(String::new(), |mut accm, chr| {
+        match chr {
+            '\\' => accm.push_str("\\\\"),
+            '\t' => accm.push_str("\\t"),
+            '\n' => accm.push_str("\\n"),
+            '\r' => accm.push_str("\\r"),
+            chr => accm.push(chr),
+        }
+        accm
+    })
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(chr) = chars.next() {
+        unescaped.push(match chr {
+            '\\' => match chars.next() {
+                Some('t') => '\t',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some(chr) => chr,
+                None => '\\',
+            },
+            chr => chr,
+        });
+    }
+    unescaped
+}
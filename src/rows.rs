@@ -1,31 +1,20 @@
-use std::{env, fs::{self, read_to_string}, io, path::{PathBuf}, io::Write};
+use std::{env, fs, io, process, path::{Path, PathBuf}, io::Write};
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-pub const TAB_STOP: usize = 8;
+use crate::config::{Config, IndentOverrides, IndentSettings};
+use crate::editorconfig::EditorConfig;
+use crate::file_format::FileFormat;
+use crate::filetype::FileType;
 
 #[derive(Default)]
 pub struct Row {
     pub row_content: String,
-    pub render: String,
 }
 
 impl Row {
-    fn new(row_content: String, render: String) -> Self {
-        Self {
-            row_content,
-            render,
-        }
-    }
-
-    pub fn insert_char(&mut self, char: char, at: usize) {
-        self.row_content.insert(at, char);
-        EditorRows::render_row(self);
-    }
-
-    pub fn delete_char(&mut self, at: usize) {
-        if at < self.row_content.len() {
-            self.row_content.remove(at);
-            EditorRows::render_row(self)
-        }
+    fn new(row_content: String) -> Self {
+        Self { row_content }
     }
 
 }
@@ -33,121 +22,391 @@ impl Row {
 pub struct EditorRows {
     contents: Vec<Row>,
     pub filename: Option<PathBuf>,
-    
+    indent: IndentSettings,
+    pub format: FileFormat,
+    // opener closer pairs inserted together while typing
+    auto_pairs: Vec<(char, char)>,
+    word_chars: String,
+    // changes with every edit, lets what is worked out from the rows be cached
+    version: u64,
+}
+
+// a version no buffer has had yet, so versions of different buffers never clash
+fn next_version() -> u64 {
+    static VERSION: AtomicU64 = AtomicU64::new(0);
+    VERSION.fetch_add(1, Ordering::Relaxed)
 }
 
 impl EditorRows {
-    pub fn new() -> Self {
+    pub fn new(config: &Config) -> Self {
         let mut arg = env::args();
         match arg.nth(1) {
-            None => Self {
-                contents: Vec::new(),
-                filename: None,
-            },
-            Some(file) => Self::from_file(file.into()),
+            None => Self::scratch(Vec::new(), config),
+            Some(file) => Self::open(file.into(), config).expect("Unable to read"),
         }
     }
-    
-    pub fn render_row(row: &mut Row) {
-        let mut idx = 0;
-        let capacity = 
-            row.row_content
-                .chars()
-                .fold(0, |acc, next| acc + if next == '\t' { 8 } else { 1 });
-        row.render = String::with_capacity(capacity);
-        row.row_content
-            .chars()
-            .for_each(|chr| {
-                idx += 1;
-                if chr == '\t' {
-                    row.render.push(' ');
-                    while idx % TAB_STOP != 0 {
-                        row.render.push(' ');
-                        idx += 1
-                    }
-                } else {
-                    row.render.push(chr);
-                }
-            });
+
+    // an unnamed buffer holding rows, e.g. a list of search results
+    pub fn scratch(rows: Vec<String>, config: &Config) -> Self {
+        let mut editor_rows = Self {
+            contents: rows.into_iter().map(Row::new).collect(),
+            filename: None,
+            indent: IndentSettings::default(),
+            format: FileFormat::default(),
+            auto_pairs: Vec::new(),
+            word_chars: String::new(),
+            version: next_version(),
+        };
+        editor_rows.configure(config);
+        editor_rows
+    }
+
+    /* @brief names a buffer that wasn't loaded from a file, its settings
+     *        are taken from config again for the filetype of the new name
+     */
+    pub fn set_filename(&mut self, filename: PathBuf, config: &Config) {
+        self.filename = Some(filename);
+        self.configure(config);
+    }
+
+    fn configure(&mut self, config: &Config) {
+        let filetype = self.filetype().name;
+        self.typing_settings(&[config], self.filetype());
+        self.indent = IndentSettings::default().apply(config.indent(filetype));
+        config.apply_format(filetype, &mut self.format);
+    }
+
+    pub fn indent(&self) -> IndentSettings {
+        self.indent
+    }
+
+    pub fn auto_pairs(&self) -> &[(char, char)] {
+        &self.auto_pairs
+    }
+
+    // characters joining words besides the ones unicode counts as letters
+    pub fn word_chars(&self) -> &str {
+        &self.word_chars
+    }
+
+    /* @brief the auto pairs and word characters of the last config setting
+     *        them, empty auto_pairs turns pairing off
+     */
+    fn typing_settings(&mut self, configs: &[&Config], filetype: &FileType) {
+        let setting = |key: &str| {
+            configs
+                .iter()
+                .rev()
+                .find_map(|config| config.get::<String>(filetype.name, key))
+        };
+        self.auto_pairs = FileType::parse_pairs(
+            &setting("auto_pairs").unwrap_or_else(|| filetype.auto_pairs.to_string()),
+        );
+        self.word_chars = setting("word_chars").unwrap_or_else(|| String::from("_"));
     }
 
-    fn from_file(file: PathBuf) -> Self {
-        let file_contents = read_to_string(&file).expect("Unable to read");
-        Self {
+    /* @brief loads file, the indentation detected from its content takes
+     *        precedence over the global config while settings from
+     *        .editorconfig and then the project config take precedence over
+     *        both, the format on disk is layered the same way
+     */
+    pub fn open(file: PathBuf, config: &Config) -> io::Result<Self> {
+        let bytes = fs::read(&file)?;
+        let filetype = FileType::from_path(Some(&file)).name;
+        let project = Config::load_project(&file);
+        let editorconfig = EditorConfig::for_file(&file);
+        let mut format = FileFormat::detect(&bytes);
+        config.apply_format(filetype, &mut format);
+        editorconfig.apply_format(&mut format);
+        project.apply_format(filetype, &mut format);
+        let mut editor_rows = Self {
             filename: Some(file),
-            contents: file_contents
-                .lines()
-                .map(|it| {
-                    let mut row = Row::new(it.into(), String::new());
-                    Self::render_row(&mut row);
-                    row
-                })
-            .collect(),
+            contents: FileFormat::split_rows(&format.charset.decode(&bytes))
+                .into_iter()
+                .map(Row::new)
+                .collect(),
+            indent: IndentSettings::default(),
+            format,
+            auto_pairs: Vec::new(),
+            word_chars: String::new(),
+            version: next_version(),
+        };
+        editor_rows.typing_settings(&[config, &project], editor_rows.filetype());
+        editor_rows.indent = IndentSettings::default()
+            .apply(config.indent(filetype))
+            .apply(editor_rows.detect_indent())
+            .apply(editorconfig.indent())
+            .apply(project.indent(filetype));
+        Ok(editor_rows)
+    }
+
+    /* @brief guesses the indentation style from leading whitespace, tabs
+     *        when more rows start with a tab than with spaces, otherwise
+     *        spaces with the width being the most common step between the
+     *        indentation of consecutive rows
+     */
+    fn detect_indent(&self) -> IndentOverrides {
+        let mut tab_rows = 0;
+        let mut space_rows = 0;
+        // how often each step width is seen, 1 to 8 spaces
+        let mut steps = [0usize; 9];
+        let mut prev_indent = 0;
+        for row in self.contents.iter().map(|row| row.row_content.as_str()) {
+            let content = row.trim_start_matches([' ', '\t']);
+            // blank rows and the * lines of block comments say nothing
+            if content.is_empty() || content.starts_with('*') {
+                continue;
+            }
+            let indent = &row[..row.len() - content.len()];
+            if indent.starts_with('\t') {
+                tab_rows += 1;
+                continue;
+            }
+            let spaces = indent.bytes().take_while(|chr| *chr == b' ').count();
+            if spaces > 0 {
+                space_rows += 1;
+            }
+            if spaces > prev_indent && spaces - prev_indent < steps.len() {
+                steps[spaces - prev_indent] += 1;
+            }
+            prev_indent = spaces;
+        }
+        if tab_rows == 0 && space_rows == 0 {
+            IndentOverrides::default()
+        } else if tab_rows > space_rows {
+            IndentOverrides {
+                expand_tabs: Some(false),
+                ..IndentOverrides::default()
+            }
+        } else {
+            // ties go to the wider step so 2 and 4 mixed reads as 4
+            let width = (2..steps.len())
+                .filter(|width| steps[*width] > 0)
+                .max_by_key(|width| (steps[*width], *width));
+            IndentOverrides {
+                indent_width: width,
+                expand_tabs: Some(true),
+                ..IndentOverrides::default()
+            }
         }
     }
 
+    pub fn filetype(&self) -> &'static FileType {
+        FileType::from_path(self.filename.as_deref())
+    }
+
     pub fn num_rows(&self) -> usize {
         self.contents.len()
     }
 
-    pub fn get_row<'a>(self:&'a Self, at: usize) -> &'a Row {
+    pub fn get_row(&self, at: usize) -> &Row {
         &self.contents[at]
     }
 
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
     pub fn get_row_mut(&mut self, at: usize) -> &mut Row {
+        self.version = next_version();
         &mut self.contents[at]
     }
 
-    pub fn get_render<'a>(self:&'a Self, at: usize) -> &'a str {
-        self.get_row(at).render.as_str()
+    pub fn insert_char(&mut self, at_y: usize, at_x: usize, char: char) {
+        self.version = next_version();
+        self.contents[at_y].row_content.insert(at_x, char);
+    }
+
+    pub fn insert_str(&mut self, at_y: usize, at_x: usize, str: &str) {
+        self.version = next_version();
+        self.contents[at_y].row_content.insert_str(at_x, str);
+    }
+
+    // removes the character starting at byte at_x
+    pub fn delete_char(&mut self, at_y: usize, at_x: usize) {
+        self.version = next_version();
+        if at_x < self.contents[at_y].row_content.len() {
+            self.contents[at_y].row_content.remove(at_x);
+        }
+    }
+
+    pub fn delete_range(&mut self, at_y: usize, from_x: usize, to_x: usize) {
+        self.version = next_version();
+        self.contents[at_y].row_content.drain(from_x..to_x);
     }
 
     pub fn insert_row(&mut self) {
+        self.version = next_version();
         self.contents.push(Row::default())
     }
 
     pub fn delete_row(&mut self, at: usize) {
+        self.version = next_version();
         self.contents.remove(at);
     }
 
     pub fn delete_row_shift_up(&mut self, at: usize) {
         let content = self.get_row(at).row_content.clone();
-        let pushed_on = self.get_row_mut(at - 1);
-        pushed_on
+        self.get_row_mut(at - 1)
             .row_content
             .push_str(content.as_str());
-        EditorRows::render_row(pushed_on);
         self.delete_row(at);
     }
 
     pub fn insert_row_at(&mut self, at: usize) {
+        self.version = next_version();
         self.contents.insert(at, Row::default());
     }
 
-    pub fn save(&self) -> io::Result<usize> {
-        match &self.filename {
-            None => Err(io::Error::new(io::ErrorKind::NotFound, "no file name specified")),
-            Some(name) => {
-                let mut file = fs::OpenOptions::new().write(true).create(true).open(name)?;
-                let content = 
-                    self.contents
-                        .iter()
-                        .fold(
-                            String::new(),
-                            |mut accm, row| {
-                                accm.push_str(row.row_content.as_str());
-                                accm.push('\n');
-                                accm
-                            });         
-                file.set_len(content.len() as u64)?;
-                if let Err(err) = file.write_all(content.as_bytes()) {
-                    return Err(err);
-                };
-                Ok(content.as_bytes().len())
+    pub fn move_row(&mut self, from: usize, to: usize) {
+        self.version = next_version();
+        let row = self.contents.remove(from);
+        self.contents.insert(to, row);
+    }
+
+    // the text of every row, used to undo edits
+    pub fn row_contents(&self) -> Vec<String> {
+        self.contents.iter().map(|row| row.row_content.clone()).collect()
+    }
+
+    // puts contents in place of the rows in range, used to undo edits
+    pub fn replace_rows(&mut self, range: Range<usize>, contents: Vec<String>) {
+        self.version = next_version();
+        self.contents.splice(range, contents.into_iter().map(Row::new));
+    }
+
+    /* @brief the save time clean up the format asks for, drops blank rows
+     *        at the end and strips trailing spaces and tabs from each row,
+     *        returns how many rows were removed or changed
+     */
+    pub fn clean_up(&mut self) -> usize {
+        self.version = next_version();
+        let mut cleaned = 0;
+        if self.format.trim_trailing_blank_lines {
+            while self
+                .contents
+                .last()
+                .is_some_and(|row| row.row_content.trim_matches([' ', '\t']).is_empty())
+            {
+                self.contents.pop();
+                cleaned += 1;
             }
         }
+        if self.format.trim_trailing_whitespace {
+            for at in 0..self.num_rows() {
+                let content = &self.contents[at].row_content;
+                let trimmed_len = content.trim_end_matches([' ', '\t']).len();
+                if trimmed_len < content.len() {
+                    self.delete_range(at, trimmed_len, content.len());
+                    cleaned += 1;
+                }
+            }
+        }
+        cleaned
+    }
+
+    /* @brief writes the rows to disk in the buffer's format, through a
+     *        temporary file renamed over the old one
+     */
+    pub fn save(&self) -> io::Result<usize> {
+        let Some(name) = &self.filename else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no file name specified"));
+        };
+        let line_ending = self.format.line_ending.as_str();
+        let mut content = self
+            .contents
+            .iter()
+            .map(|row| row.row_content.as_str())
+            .collect::<Vec<&str>>()
+            .join(line_ending);
+        if self.format.final_newline && !self.contents.is_empty() {
+            content.push_str(line_ending);
+        }
+        let bytes = self.format.charset.encode(&content);
+        write_atomic(name, &bytes)?;
+        Ok(bytes.len())
     }
 
 }
 
+/* @brief writes bytes to a temporary file next to path and renames it over
+ *        path, so that a failed write leaves the old content in place, a
+ *        symlink is written through and the permissions of path are kept
+ */
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let Some(name) = path.file_name() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a file name"));
+    };
+    let temp = path.with_file_name(format!(".{}.{}.tmp", name.to_string_lossy(), process::id()));
+    let written = fs::File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        })
+        .and_then(|_| match fs::metadata(&path) {
+            Ok(metadata) => fs::set_permissions(&temp, metadata.permissions()),
+            Err(_) => Ok(()),
+        })
+        .and_then(|_| fs::rename(&temp, &path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    // a directory only this test writes to, so tests running at once don't clash
+    fn scratch_dir(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("text_editor_rows_{}_{}", process::id(), name));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    fn entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn write_atomic_replaces_the_file_or_leaves_it() {
+        let dir = scratch_dir("atomic");
+        let path = dir.join("file.txt");
+        write_atomic(&path, b"new file\n").unwrap();
+        write_atomic(&path, b"second\n").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second\n");
+        assert_eq!(entries(&dir), ["file.txt"]);
+        // the temporary file can't be made, the old content stays
+        let temp = dir.join(format!(".file.txt.{}.tmp", process::id()));
+        fs::create_dir(&temp).unwrap();
+        assert!(write_atomic(&path, b"lost\n").is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"second\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_atomic_keeps_permissions_and_symlinks() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+        let dir = scratch_dir("links");
+        let target = dir.join("target.sh");
+        fs::write(&target, "old\n").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o750)).unwrap();
+        let link = dir.join("link.sh");
+        symlink(&target, &link).unwrap();
+        write_atomic(&link, b"new\n").unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read(&target).unwrap(), b"new\n");
+        assert_eq!(fs::metadata(&target).unwrap().permissions().mode() & 0o777, 0o750);
+        assert_eq!(entries(&dir), ["link.sh", "target.sh"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
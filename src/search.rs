@@ -1,14 +1,34 @@
+use std::iter;
+use std::ops::Range;
+
+use caseless::{default_case_fold_str, Caseless};
+
+#[derive(Clone, Copy, PartialEq)]
 pub enum SearchDirection {
     Forward,
     Backward,
 }
 
+impl SearchDirection {
+    pub fn reverse(self) -> Self {
+        match self {
+            SearchDirection::Forward => SearchDirection::Backward,
+            SearchDirection::Backward => SearchDirection::Forward,
+        }
+    }
+}
+
 pub struct SearchIndex {
     pub x_index: usize,
     pub y_index: usize,
-    pub x_direction: Option<SearchDirection>,
-    pub y_direction: Option<SearchDirection>,
-
+    pub direction: Option<SearchDirection>,
+    // the last search accepted with Enter, repeated with F3 once the prompt is closed
+    pub keyword: Option<String>,
+    // the way the last search stepped between matches
+    pub last_direction: SearchDirection,
+    pub case_mode: CaseMode,
+    // the selection the open search prompt is limited to, start and end as (x, y)
+    pub bounds: Option<((usize, usize), (usize, usize))>,
 }
 
 impl SearchIndex {
@@ -16,16 +36,230 @@ impl SearchIndex {
         Self {
             x_index: 0,
             y_index: 0,
-            x_direction: None,
-            y_direction: None,
+            direction: None,
+            keyword: None,
+            last_direction: SearchDirection::Forward,
+            case_mode: CaseMode::Sensitive,
+            bounds: None,
         }
     }
 
+    /* @brief forgets the position and bounds once the prompt closes, the
+     *        keyword and direction stay for repeating the search anywhere
+     */
     pub fn reset(&mut self) {
         self.y_index = 0;
         self.x_index = 0;
-        self.y_direction = None;
-        self.x_direction = None;
+        self.direction = None;
+        self.bounds = None;
+    }
+}
+
+// how letter case counts when matching a search keyword
+#[derive(Clone, Copy, PartialEq)]
+pub enum CaseMode {
+    Sensitive,
+    Insensitive,
+    // insensitive unless the keyword has an uppercase letter
+    Smart,
+}
+
+impl CaseMode {
+    pub fn next(self) -> Self {
+        match self {
+            CaseMode::Sensitive => CaseMode::Insensitive,
+            CaseMode::Insensitive => CaseMode::Smart,
+            CaseMode::Smart => CaseMode::Sensitive,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CaseMode::Sensitive => "match case",
+            CaseMode::Insensitive => "ignore case",
+            CaseMode::Smart => "smart case",
+        }
+    }
+
+    pub fn ignores_case(self, keyword: &str) -> bool {
+        match self {
+            CaseMode::Sensitive => false,
+            CaseMode::Insensitive => true,
+            CaseMode::Smart => !keyword.chars().any(char::is_uppercase),
+        }
     }
 }
 
+/* @brief the byte ranges of text where keyword occurs without overlapping,
+ *        ignoring case compares the full Unicode case folding of both so
+ *        e.g. Straße matches STRASSE, a match has to start and end on
+ *        whole characters of text
+ */
+pub fn find_all(text: &str, keyword: &str, ignore_case: bool) -> Vec<Range<usize>> {
+    if keyword.is_empty() {
+        return Vec::new();
+    }
+    if !ignore_case {
+        return text
+            .match_indices(keyword)
+            .map(|(idx, found)| idx..idx + found.len())
+            .collect();
+    }
+    let needle = default_case_fold_str(keyword);
+    // the byte of text each byte of the folded text came from
+    let mut folded = String::new();
+    let mut origin = Vec::new();
+    for (idx, chr) in text.char_indices() {
+        folded.extend(iter::once(chr).default_case_fold());
+        origin.resize(folded.len(), idx);
+    }
+    origin.push(text.len());
+    let starts_char = |at: usize| at == 0 || at == folded.len() || origin[at - 1] != origin[at];
+    let mut found = Vec::new();
+    let mut from = 0;
+    while let Some(idx) = folded[from..].find(&needle).map(|idx| from + idx) {
+        let end = idx + needle.len();
+        if starts_char(idx) && starts_char(end) {
+            found.push(origin[idx]..origin[end]);
+            from = end;
+        } else {
+            // a match splitting a character takes nothing, one may start inside it
+            from = idx + folded[idx..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    found
+}
+
+/* @brief the match nearest (x, y) going in direction in a buffer of
+ *        num_rows rows and whether the search wrapped around the end of
+ *        the buffer to get there, matches gives where they start on a
+ *        row, None searches from (x, y) itself and Forward and Backward
+ *        from just after and before it
+ */
+pub fn nearest_match(
+    num_rows: usize,
+    (x, y): (usize, usize),
+    direction: Option<SearchDirection>,
+    matches: impl Fn(usize) -> Vec<usize>,
+) -> Option<((usize, usize), bool)> {
+    if num_rows == 0 {
+        return None;
+    }
+    // a cursor below the last row searches from the end of it
+    let (x, y) = if y >= num_rows { (usize::MAX, num_rows - 1) } else { (x, y) };
+    // the cursor row is searched again last for the part not searched first
+    if direction == Some(SearchDirection::Backward) {
+        (0..=num_rows).find_map(|step| {
+            let at = (y + num_rows - step) % num_rows;
+            let found = matches(at).into_iter().rev().find(|pos| match step {
+                0 => *pos < x,
+                step if step == num_rows => *pos >= x,
+                _ => true,
+            });
+            found.map(|pos| ((pos, at), step > y))
+        })
+    } else {
+        let start = if direction.is_some() { x.saturating_add(1) } else { x };
+        (0..=num_rows).find_map(|step| {
+            let at = (y + step) % num_rows;
+            let found = matches(at).into_iter().find(|pos| match step {
+                0 => *pos >= start,
+                step if step == num_rows => *pos < start,
+                _ => true,
+            });
+            found.map(|pos| ((pos, at), y + step >= num_rows))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_case_exactly_unless_told_otherwise() {
+        assert_eq!(find_all("abAB ab", "ab", false), vec![0..2, 5..7]);
+        assert_eq!(find_all("abAB ab", "ab", true), vec![0..2, 2..4, 5..7]);
+        assert_eq!(find_all("aaaa", "aa", false), vec![0..2, 2..4]);
+        assert!(find_all("abc", "", true).is_empty());
+    }
+
+    #[test]
+    fn folds_case_fully() {
+        assert_eq!(find_all("Straße", "STRASSE", true), vec![0..7]);
+        assert_eq!(find_all("STRASSE", "straße", true), vec![0..7]);
+        assert!(find_all("Straße", "STRASSE", false).is_empty());
+    }
+
+    #[test]
+    fn ranges_are_bytes_of_the_original_text() {
+        assert_eq!(find_all("ÄpfEL äpfel", "äpfel", true), vec![0..6, 7..13]);
+        let text = "x ΣΊΣΥΦΟΣ y";
+        let found = find_all(text, "σίσυφος", true);
+        assert_eq!(found.iter().map(|range| &text[range.clone()]).collect::<Vec<_>>(), ["ΣΊΣΥΦΟΣ"]);
+    }
+
+    #[test]
+    fn matches_cover_whole_characters() {
+        // ß folds to ss, half of it isn't a match
+        assert!(find_all("ß", "s", true).is_empty());
+        assert_eq!(find_all("aßs", "ss", true), vec![1..3]);
+        // the half of ß that doesn't fit doesn't hide the match after it
+        assert_eq!(find_all("sß", "ss", true), vec![1..3]);
+        assert_eq!(find_all("sßs", "ss", true), vec![1..3]);
+        assert_eq!(find_all("ßß", "ss", true), vec![0..2, 2..4]);
+    }
+
+    // rows of a buffer and where matches start on each
+    fn rows(starts: &[&'static [usize]]) -> impl Fn(usize) -> Vec<usize> {
+        let starts = starts.to_vec();
+        move |at| starts[at].to_vec()
+    }
+
+    #[test]
+    fn wraps_around_a_single_row() {
+        let one = rows(&[&[2, 6]]);
+        let forward = Some(SearchDirection::Forward);
+        let backward = Some(SearchDirection::Backward);
+        assert_eq!(nearest_match(1, (2, 0), None, &one), Some(((2, 0), false)));
+        assert_eq!(nearest_match(1, (2, 0), forward, &one), Some(((6, 0), false)));
+        // past the last match the cursor row is checked again after wrapping
+        assert_eq!(nearest_match(1, (6, 0), forward, &one), Some(((2, 0), true)));
+        assert_eq!(nearest_match(1, (6, 0), backward, &one), Some(((2, 0), false)));
+        assert_eq!(nearest_match(1, (2, 0), backward, &one), Some(((6, 0), true)));
+        // the only match is found again when the cursor is on it
+        let single = rows(&[&[3]]);
+        assert_eq!(nearest_match(1, (3, 0), forward, &single), Some(((3, 0), true)));
+        assert_eq!(nearest_match(1, (3, 0), backward, &single), Some(((3, 0), true)));
+        assert_eq!(nearest_match(1, (0, 0), forward, rows(&[&[]])), None);
+        assert_eq!(nearest_match(0, (0, 0), None, rows(&[])), None);
+    }
+
+    #[test]
+    fn wraps_around_several_rows() {
+        let three = rows(&[&[1], &[], &[0, 4]]);
+        let forward = Some(SearchDirection::Forward);
+        let backward = Some(SearchDirection::Backward);
+        assert_eq!(nearest_match(3, (0, 1), forward, &three), Some(((0, 2), false)));
+        assert_eq!(nearest_match(3, (4, 2), forward, &three), Some(((1, 0), true)));
+        assert_eq!(nearest_match(3, (0, 1), backward, &three), Some(((1, 0), false)));
+        assert_eq!(nearest_match(3, (1, 0), backward, &three), Some(((4, 2), true)));
+        assert_eq!(nearest_match(3, (2, 2), backward, &three), Some(((0, 2), false)));
+        // the part of the cursor row before it is only reached after wrapping
+        let first = rows(&[&[0, 5], &[], &[]]);
+        assert_eq!(nearest_match(3, (5, 0), forward, &first), Some(((0, 0), true)));
+        assert_eq!(nearest_match(3, (0, 0), backward, &first), Some(((5, 0), true)));
+    }
+
+    #[test]
+    fn searches_from_the_end_below_the_last_row() {
+        let three = rows(&[&[1], &[], &[0, 4]]);
+        let forward = Some(SearchDirection::Forward);
+        let backward = Some(SearchDirection::Backward);
+        assert_eq!(nearest_match(3, (0, 3), None, &three), Some(((1, 0), true)));
+        assert_eq!(nearest_match(3, (0, 3), forward, &three), Some(((1, 0), true)));
+        assert_eq!(nearest_match(3, (0, 3), backward, &three), Some(((4, 2), false)));
+        let last = rows(&[&[], &[], &[2]]);
+        assert_eq!(nearest_match(3, (0, 7), forward, &last), Some(((2, 2), true)));
+    }
+}
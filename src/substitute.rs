@@ -0,0 +1,166 @@
+use std::ops::Range;
+
+/* @brief a replace command in the form `[range]s/old/new/[flags]`, the
+ *        range being `%` for every row, `.` for the cursor row, `$` for the
+ *        last row, a row number or two of these separated by a comma, a
+ *        leading `:` is allowed and any punctuation can stand in for `/`
+ */
+pub struct Substitute {
+    // rows to replace in, None when the command gave no range
+    pub rows: Option<Range<usize>>,
+    pub pattern: String,
+    pub replacement: String,
+    // replace every match on a row instead of only the first
+    pub global: bool,
+    // Some(true) for the i flag and Some(false) for I, None leaves it to the search mode
+    pub ignore_case: Option<bool>,
+}
+
+impl Substitute {
+    /* @brief reads input with row numbers counted from 1, cursor_y and
+     *        num_rows resolve `.`, `$` and `%`
+     */
+    pub fn parse(input: &str, cursor_y: usize, num_rows: usize) -> Result<Self, String> {
+        let input = input.trim_start().trim_start_matches(':');
+        let command_at = input
+            .find(|chr: char| chr.is_alphabetic())
+            .ok_or_else(|| String::from("Expected s/old/new/"))?;
+        let (range, command) = input.split_at(command_at);
+        let rows = parse_range(range.trim(), cursor_y, num_rows)?;
+        let mut chars = command.chars();
+        if chars.next() != Some('s') {
+            return Err(format!("Unknown command {}", command));
+        }
+        let delimiter = chars
+            .next()
+            .filter(|chr| chr.is_ascii_punctuation() && *chr != '\\')
+            .ok_or_else(|| String::from("Expected a delimiter after s"))?;
+        let mut parts = split_unescaped(chars.as_str(), delimiter);
+        let pattern = parts.next().unwrap_or_default();
+        if pattern.is_empty() {
+            return Err(String::from("Nothing to replace"));
+        }
+        let replacement = parts.next().unwrap_or_default();
+        let mut global = false;
+        let mut ignore_case = None;
+        for flag in parts.next().unwrap_or_default().chars() {
+            match flag {
+                'g' => global = true,
+                'i' => ignore_case = Some(true),
+                'I' => ignore_case = Some(false),
+                flag => return Err(format!("Unknown flag {}", flag)),
+            }
+        }
+        Ok(Self {
+            rows,
+            pattern,
+            replacement,
+            global,
+            ignore_case,
+        })
+    }
+}
+
+fn parse_range(range: &str, cursor_y: usize, num_rows: usize) -> Result<Option<Range<usize>>, String> {
+    if range.is_empty() {
+        return Ok(None);
+    }
+    if range == "%" {
+        return Ok(Some(0..num_rows));
+    }
+    let row = |address: &str| -> Result<usize, String> {
+        match address.trim() {
+            "." => Ok(cursor_y),
+            "$" => Ok(num_rows.saturating_sub(1)),
+            number => match number.parse::<usize>() {
+                Ok(number) if number > 0 => Ok(number - 1),
+                _ => Err(format!("Invalid row {}", number)),
+            },
+        }
+    };
+    let (start, end) = match range.split_once(',') {
+        Some((start, end)) => (row(start)?, row(end)?),
+        None => (row(range)?, row(range)?),
+    };
+    if start > end {
+        return Err(format!("Backwards range {}", range));
+    }
+    Ok(Some(start..end + 1))
+}
+
+/* @brief the parts of text between delimiters, a backslash before the
+ *        delimiter or another backslash escapes it, \t is a tab and other
+ *        backslashes are kept as they are
+ */
+fn split_unescaped(text: &str, delimiter: char) -> impl Iterator<Item = String> {
+    let mut parts = vec![String::new()];
+    let mut chars = text.chars();
+    while let Some(chr) = chars.next() {
+        let part = parts.last_mut().unwrap();
+        match chr {
+            '\\' => match chars.next() {
+                Some(chr) if chr == delimiter || chr == '\\' => part.push(chr),
+                Some('t') => part.push('\t'),
+                Some(chr) => {
+                    part.push('\\');
+                    part.push(chr);
+                }
+                None => part.push('\\'),
+            },
+            chr if chr == delimiter => parts.push(String::new()),
+            chr => part.push(chr),
+        }
+    }
+    parts.into_iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pattern_replacement_and_flags() {
+        let command = Substitute::parse("s/old/new/gi", 0, 10).unwrap();
+        assert_eq!(command.rows, None);
+        assert_eq!((command.pattern.as_str(), command.replacement.as_str()), ("old", "new"));
+        assert!(command.global);
+        assert_eq!(command.ignore_case, Some(true));
+        let command = Substitute::parse(":s#a/b#c#I", 0, 10).unwrap();
+        assert_eq!((command.pattern.as_str(), command.replacement.as_str()), ("a/b", "c"));
+        assert!(!command.global);
+        assert_eq!(command.ignore_case, Some(false));
+        // the closing delimiter and the replacement can be left out
+        let command = Substitute::parse("s/old", 0, 10).unwrap();
+        assert_eq!((command.replacement.as_str(), command.ignore_case), ("", None));
+    }
+
+    #[test]
+    fn parses_ranges() {
+        let rows = |input: &str| Substitute::parse(input, 4, 10).map(|command| command.rows);
+        assert_eq!(rows("%s/a/b/"), Ok(Some(0..10)));
+        assert_eq!(rows(".s/a/b/"), Ok(Some(4..5)));
+        assert_eq!(rows("$s/a/b/"), Ok(Some(9..10)));
+        assert_eq!(rows("2,.s/a/b/"), Ok(Some(1..5)));
+        assert_eq!(rows(" 3 , $ s/a/b/"), Ok(Some(2..10)));
+        assert_eq!(Substitute::parse("%s/a/b/", 0, 0).unwrap().rows, Some(0..0));
+    }
+
+    #[test]
+    fn unescapes_delimiters_and_tabs() {
+        let command = Substitute::parse(r"s/a\/b\\/c\td\n/", 0, 1).unwrap();
+        assert_eq!(command.pattern, r"a/b\");
+        assert_eq!(command.replacement, "c\td\\n");
+    }
+
+    #[test]
+    fn reports_bad_commands() {
+        let error = |input: &str| Substitute::parse(input, 0, 10).err();
+        assert_eq!(error("").as_deref(), Some("Expected s/old/new/"));
+        assert_eq!(error("x/a/b/").as_deref(), Some("Unknown command x/a/b/"));
+        assert_eq!(error("sa").as_deref(), Some("Expected a delimiter after s"));
+        assert_eq!(error("s//b/").as_deref(), Some("Nothing to replace"));
+        assert_eq!(error("s/a/b/q").as_deref(), Some("Unknown flag q"));
+        assert_eq!(error("0s/a/b/").as_deref(), Some("Invalid row 0"));
+        assert_eq!(error("5,2s/a/b/").as_deref(), Some("Backwards range 5,2"));
+    }
+}
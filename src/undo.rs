@@ -0,0 +1,219 @@
+use std::cmp::min;
+
+use crate::rows::EditorRows;
+
+/* @brief the contents of the buffer and the cursor before an edit
+ */
+struct Snapshot {
+    rows: Vec<String>,
+    cursor: (usize, usize),
+}
+
+/* @brief the rows an undo step changed, from at on the rows before were
+ *        replaced with the rows after, with the cursor on either side
+ */
+struct Change {
+    at: usize,
+    before: Vec<String>,
+    after: Vec<String>,
+    cursor_before: (usize, usize),
+    cursor_after: (usize, usize),
+}
+
+impl Change {
+    // the rows that differ between before and now, None when nothing does
+    fn between(before: Snapshot, rows: &EditorRows, cursor: (usize, usize)) -> Option<Self> {
+        let mut old = before.rows;
+        let row = |at: usize| rows.get_row(at).row_content.as_str();
+        let prefix = old
+            .iter()
+            .enumerate()
+            .take_while(|(at, content)| *at < rows.num_rows() && row(*at) == content.as_str())
+            .count();
+        let max_suffix = min(old.len(), rows.num_rows()) - prefix;
+        let suffix = (0..max_suffix)
+            .take_while(|back| old[old.len() - 1 - back] == row(rows.num_rows() - 1 - back))
+            .count();
+        if prefix == old.len() && prefix == rows.num_rows() {
+            return None;
+        }
+        old.truncate(old.len() - suffix);
+        Some(Self {
+            at: prefix,
+            before: old.split_off(prefix),
+            after: (prefix..rows.num_rows() - suffix).map(|at| row(at).to_string()).collect(),
+            cursor_before: before.cursor,
+            cursor_after: cursor,
+        })
+    }
+}
+
+// kinds of edits, a run of typing or deleting is undone in one step
+#[derive(Clone, Copy, PartialEq)]
+pub enum EditKind {
+    Insert,
+    Delete,
+    Other,
+}
+
+const UNDO_LIMIT: usize = 200;
+
+pub struct UndoHistory {
+    undo: Vec<Change>,
+    redo: Vec<Change>,
+    // the buffer before the step still being made, diffed once it ends
+    open: Option<Snapshot>,
+    // the kind of the edit the open step was started for, None once sealed
+    last_edit: Option<EditKind>,
+}
+
+impl UndoHistory {
+    pub fn new() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            open: None,
+            last_edit: None,
+        }
+    }
+
+    /* @brief remembers rows before an edit of kind, an insert or delete
+     *        following one of the same kind joins the open undo step without
+     *        copying anything, any other edit ends it and starts a new one
+     */
+    pub fn record(&mut self, kind: EditKind, rows: &EditorRows, cursor: (usize, usize)) {
+        let joins = kind != EditKind::Other && self.last_edit == Some(kind);
+        self.last_edit = Some(kind);
+        if joins {
+            return;
+        }
+        self.close(rows, cursor);
+        self.open = Some(Snapshot {
+            rows: rows.row_contents(),
+            cursor,
+        });
+    }
+
+    // ends the current run of typing, the next edit starts a new step
+    pub fn seal(&mut self) {
+        self.last_edit = None;
+    }
+
+    // turns the open step into a change, dropped when it changed nothing
+    fn close(&mut self, rows: &EditorRows, cursor: (usize, usize)) {
+        let Some(change) = self.open.take().and_then(|open| Change::between(open, rows, cursor)) else {
+            return;
+        };
+        self.redo.clear();
+        self.undo.push(change);
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+    }
+
+    // takes rows back one step, returns where the cursor was before it
+    pub fn undo(&mut self, rows: &mut EditorRows, cursor: (usize, usize)) -> Option<(usize, usize)> {
+        self.close(rows, cursor);
+        self.last_edit = None;
+        let change = self.undo.pop()?;
+        rows.replace_rows(change.at..change.at + change.after.len(), change.before.clone());
+        let cursor = change.cursor_before;
+        self.redo.push(change);
+        Some(cursor)
+    }
+
+    // makes the last undone step again, returns where the cursor was after it
+    pub fn redo(&mut self, rows: &mut EditorRows, cursor: (usize, usize)) -> Option<(usize, usize)> {
+        self.close(rows, cursor);
+        self.last_edit = None;
+        let change = self.redo.pop()?;
+        rows.replace_rows(change.at..change.at + change.before.len(), change.after.clone());
+        let cursor = change.cursor_after;
+        self.undo.push(change);
+        Some(cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn rows(contents: &[&str]) -> EditorRows {
+        EditorRows::scratch(contents.iter().map(|row| row.to_string()).collect(), &Config::default())
+    }
+
+    fn change(before: &[&str], after: &[&str]) -> Option<Change> {
+        let snapshot = Snapshot {
+            rows: before.iter().map(|row| row.to_string()).collect(),
+            cursor: (0, 0),
+        };
+        Change::between(snapshot, &rows(after), (1, 1))
+    }
+
+    #[test]
+    fn keeps_only_the_rows_that_changed() {
+        let inserted = change(&["a", "b", "c"], &["a", "x", "b", "c"]).unwrap();
+        assert_eq!((inserted.at, inserted.before, inserted.after), (1, vec![], vec!["x".to_string()]));
+        let deleted = change(&["a", "b", "c"], &["a", "c"]).unwrap();
+        assert_eq!((deleted.at, deleted.before, deleted.after), (1, vec!["b".to_string()], vec![]));
+        let moved = change(&["a", "b", "c"], &["b", "a", "c"]).unwrap();
+        assert_eq!(moved.at, 0);
+        assert_eq!(moved.before, vec!["a", "b"]);
+        assert_eq!(moved.after, vec!["b", "a"]);
+        // equal rows may only count once, as part of the prefix or the suffix
+        let repeated = change(&["a", "a"], &["a", "a", "a"]).unwrap();
+        assert_eq!((repeated.at, repeated.before, repeated.after), (2, vec![], vec!["a".to_string()]));
+        let changed = change(&["a", "b"], &["a", "x"]).unwrap();
+        assert_eq!((changed.cursor_before, changed.cursor_after), ((0, 0), (1, 1)));
+    }
+
+    #[test]
+    fn drops_steps_that_changed_nothing() {
+        assert!(change(&["a", "b"], &["a", "b"]).is_none());
+        assert!(change(&[], &[]).is_none());
+        let mut history = UndoHistory::new();
+        let mut rows = rows(&["a", "b"]);
+        history.record(EditKind::Other, &rows, (0, 0));
+        assert_eq!(history.undo(&mut rows, (1, 0)), None);
+        assert_eq!(rows.row_contents(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn undo_and_redo_round_trip() {
+        let mut history = UndoHistory::new();
+        let mut rows = rows(&["a", "b", "c"]);
+        history.record(EditKind::Other, &rows, (0, 1));
+        rows.delete_row(1);
+        history.record(EditKind::Other, &rows, (0, 1));
+        rows.insert_row_at(0);
+        rows.get_row_mut(0).row_content.push('x');
+        assert_eq!(history.undo(&mut rows, (1, 0)), Some((0, 1)));
+        assert_eq!(rows.row_contents(), vec!["a", "c"]);
+        assert_eq!(history.undo(&mut rows, (0, 1)), Some((0, 1)));
+        assert_eq!(rows.row_contents(), vec!["a", "b", "c"]);
+        assert_eq!(history.undo(&mut rows, (0, 1)), None);
+        assert_eq!(history.redo(&mut rows, (0, 1)), Some((0, 1)));
+        assert_eq!(rows.row_contents(), vec!["a", "c"]);
+        assert_eq!(history.redo(&mut rows, (0, 1)), Some((1, 0)));
+        assert_eq!(rows.row_contents(), vec!["x", "a", "c"]);
+        assert_eq!(history.redo(&mut rows, (1, 0)), None);
+    }
+
+    #[test]
+    fn typing_joins_one_step_until_sealed() {
+        let mut history = UndoHistory::new();
+        let mut rows = rows(&[""]);
+        for (x, chr) in "ab".chars().enumerate() {
+            history.record(EditKind::Insert, &rows, (x, 0));
+            rows.insert_char(0, x, chr);
+        }
+        history.seal();
+        history.record(EditKind::Insert, &rows, (2, 0));
+        rows.insert_char(0, 2, 'c');
+        assert_eq!(history.undo(&mut rows, (3, 0)), Some((2, 0)));
+        assert_eq!(rows.row_contents(), vec!["ab"]);
+        assert_eq!(history.undo(&mut rows, (2, 0)), Some((0, 0)));
+        assert_eq!(rows.row_contents(), vec![""]);
+    }
+}
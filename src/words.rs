@@ -0,0 +1,60 @@
+/* @brief word boundaries within a row, words come from unicode word
+ *        segmentation with runs of extra word characters (e.g. - in css)
+ *        joined to the words around them
+ */
+
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+/* @brief byte ranges of the words in text, a run of punctuation that isn't
+ *        a word character is a word of its own and whitespace separates
+ */
+fn words(text: &str, extra: &str) -> Vec<Range<usize>> {
+    let is_word = |segment: &str| {
+        segment.chars().any(|chr| chr.is_alphanumeric())
+            || segment.chars().all(|chr| extra.contains(chr))
+    };
+    let mut words: Vec<Range<usize>> = Vec::new();
+    // whether the last word is made of word characters, None after whitespace
+    let mut last_kind = None;
+    for (idx, segment) in text.split_word_bound_indices() {
+        if segment.chars().all(char::is_whitespace) {
+            last_kind = None;
+            continue;
+        }
+        let kind = Some(is_word(segment));
+        match words.last_mut() {
+            Some(last) if last.end == idx && kind == last_kind => last.end = idx + segment.len(),
+            _ => words.push(idx..idx + segment.len()),
+        }
+        last_kind = kind;
+    }
+    words
+}
+
+// the end of the first word ending after from
+pub fn next_word_end(text: &str, from: usize, extra: &str) -> Option<usize> {
+    words(text, extra)
+        .into_iter()
+        .map(|word| word.end)
+        .find(|end| *end > from)
+}
+
+// the start of the last word starting before from
+pub fn prev_word_start(text: &str, from: usize, extra: &str) -> Option<usize> {
+    words(text, extra)
+        .into_iter()
+        .map(|word| word.start)
+        .rev()
+        .find(|start| *start < from)
+}
+
+// the word containing byte at or ending right at it
+pub fn word_at(text: &str, at: usize, extra: &str) -> Option<Range<usize>> {
+    let words = words(text, extra);
+    words
+        .iter()
+        .find(|word| word.contains(&at))
+        .or_else(|| words.iter().find(|word| word.end == at))
+        .cloned()
+}